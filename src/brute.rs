@@ -0,0 +1,72 @@
+//! a brute-force oracle for tiny networks, only built for the unit tests
+use crate::graph::{GraphNetwork, NodeId};
+use crate::karzanov::{KarzanovArc, KarzanovNode};
+
+/// the capacity of the least cut over every split of the nodes between the source and the sink sides,
+/// which equals the max-flow value. it tries 2^(nodes - 2) splits, so keep to a handful of nodes
+pub fn maxflow_brute(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> u32 {
+    if source_id == sink_id {
+        return 0;
+    }
+    let others: Vec<NodeId> = (0..network.node_data.len())
+        .filter(|node_id| *node_id != source_id && *node_id != sink_id)
+        .collect();
+    assert!(others.len() <= 16, "too many nodes to try every cut");
+    (0..1u32 << others.len())
+        .map(|split| {
+            let mut source_side = vec![false; network.node_data.len()];
+            source_side[source_id] = true;
+            for (i, node_id) in others.iter().enumerate() {
+                source_side[*node_id] = split >> i & 1 == 1;
+            }
+            (0..network.arc_data.len())
+                .filter_map(|arc_id| network.arc_endpoints(arc_id).map(|ends| (arc_id, ends)))
+                .filter(|(_, (from, into))| source_side[*from] && !source_side[*into])
+                .map(|(arc_id, _)| network.data_of_arc(arc_id).unwrap().capacity)
+                .sum()
+        })
+        .min()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::random_layered;
+    use crate::instances::*;
+    use crate::karzanov::maxflow;
+
+    #[test]
+    fn instances() {
+        for ((source, sink, network), flow_value) in [
+            (network_instance1(), 5),
+            (network_instance2(), 6),
+            (network_instance3(), 1),
+            (network_instance4(), 1),
+        ] {
+            assert_eq!(maxflow_brute(source, sink, &network), flow_value);
+        }
+    }
+
+    #[test]
+    fn karzanov_on_small_capacities() {
+        // up to 8 nodes, with capacities of at most 3 so that partial pops of the stacks are common
+        for seed in 0..300 {
+            let layers = 1 + seed as usize % 3;
+            let width = 1 + seed as usize / 3 % (6 / layers);
+            let (source, sink, mut network) = random_layered(layers, width, 3, seed);
+            assert!(network.node_data.len() <= 8);
+            let expected = maxflow_brute(source, sink, &network);
+            assert_eq!(
+                maxflow(source, sink, &mut network),
+                Ok(expected),
+                "seed {}",
+                seed
+            );
+        }
+    }
+}
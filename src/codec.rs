@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::dimacs::MAX_NODES;
+use crate::graph::{ArcId, GraphNetwork, NodeId};
+use crate::karzanov::{KarzanovArc, KarzanovNode};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// the input ended in the middle of a field
+    UnexpectedEnd,
+    /// an arc refers to a node beyond the node count
+    NodeOutOfRange(NodeId),
+    /// the number of bytes left over after the last arc
+    TrailingBytes(usize),
+    /// the node count given is more than `MAX_NODES`, as the nodes are all allocated up front
+    TooManyNodes(usize),
+    /// the arc of this number carries more than its capacity
+    FlowExceedsCapacity(ArcId),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "unexpected end of input"),
+            DecodeError::NodeOutOfRange(node) => write!(f, "node {} is out of range", node),
+            DecodeError::TrailingBytes(n) => write!(f, "{} trailing bytes after the last arc", n),
+            DecodeError::TooManyNodes(count) => write!(f, "{} nodes are too many", count),
+            DecodeError::FlowExceedsCapacity(arc_id) => {
+                write!(f, "arc {} carries more than its capacity", arc_id)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+}
+
+impl Reader<'_> {
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        let (head, rest) = self
+            .bytes
+            .split_first_chunk::<4>()
+            .ok_or(DecodeError::UnexpectedEnd)?;
+        self.bytes = rest;
+        Ok(u32::from_le_bytes(*head))
+    }
+}
+
+impl GraphNetwork<KarzanovNode, KarzanovArc> {
+    /// encode the network as little-endian `u32`s:
+    /// node count, arc count, then `from, into, capacity, flow` for each arc.
+    /// removed nodes and arcs are dropped, so the ids are compacted in the same way as `clean`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut old_new_map = HashMap::<NodeId, NodeId>::new();
+        for (old_node_id, node) in self.node_data.iter().enumerate() {
+            if node.is_some() {
+                old_new_map.insert(old_node_id, old_new_map.len());
+            }
+        }
+        let arcs: Vec<(NodeId, NodeId, &KarzanovArc)> = self
+            .arc_data
+            .iter()
+            .enumerate()
+            .filter_map(|(arc_id, arc)| {
+                let (from, into) = self.arc_endpoints(arc_id)?;
+                Some((old_new_map[&from], old_new_map[&into], arc.as_ref()?))
+            })
+            .collect();
+
+        let mut bytes = Vec::with_capacity(4 * (2 + 4 * arcs.len()));
+        bytes.extend((old_new_map.len() as u32).to_le_bytes());
+        bytes.extend((arcs.len() as u32).to_le_bytes());
+        for (from, into, arc) in arcs {
+            bytes.extend((from as u32).to_le_bytes());
+            bytes.extend((into as u32).to_le_bytes());
+            bytes.extend(arc.capacity.to_le_bytes());
+            bytes.extend(arc.flow.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// decode a network written by `to_bytes`.
+    /// the flows are restored, while the internal states of the nodes start fresh.
+    /// fail on more than `MAX_NODES` nodes or on a flow above its capacity
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader { bytes };
+        let node_count = reader.u32()? as usize;
+        let arc_count = reader.u32()? as usize;
+        if node_count > MAX_NODES {
+            return Err(DecodeError::TooManyNodes(node_count));
+        }
+
+        let mut network = GraphNetwork::new();
+        network.add_nodes((0..node_count).map(|_| KarzanovNode::new()));
+        for arc_id in 0..arc_count {
+            let from = reader.u32()? as NodeId;
+            let into = reader.u32()? as NodeId;
+            let capacity = reader.u32()?;
+            let flow = reader.u32()?;
+            for node in [from, into] {
+                if node >= node_count {
+                    return Err(DecodeError::NodeOutOfRange(node));
+                }
+            }
+            if flow > capacity {
+                return Err(DecodeError::FlowExceedsCapacity(arc_id));
+            }
+            let mut arc = KarzanovArc::new(capacity);
+            arc.flow = flow;
+            network.connect(from, into, arc);
+        }
+        if !reader.bytes.is_empty() {
+            return Err(DecodeError::TrailingBytes(reader.bytes.len()));
+        }
+        Ok(network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instances::network_instance1;
+    use crate::karzanov::maxflow;
+
+    #[test]
+    fn round_trip() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+
+        let decoded = GraphNetwork::from_bytes(&network.to_bytes()).unwrap();
+        assert_eq!(decoded.node_data.len(), network.node_data.len());
+        assert_eq!(decoded.arc_data.len(), network.arc_data.len());
+        for arc_id in 0..network.arc_data.len() {
+            let arc = network.data_of_arc(arc_id).unwrap();
+            let decoded_arc = decoded.data_of_arc(arc_id).unwrap();
+            assert_eq!(decoded.arc_endpoints(arc_id), network.arc_endpoints(arc_id));
+            assert_eq!(decoded_arc.capacity, arc.capacity);
+            assert_eq!(decoded_arc.flow, arc.flow);
+        }
+    }
+
+    #[test]
+    fn removed_slots_are_compacted() {
+        let (_, _, mut network) = network_instance1();
+        network.remove_node(4);
+        network.disconnect(4);
+        network.disconnect(6);
+
+        let decoded = GraphNetwork::from_bytes(&network.to_bytes()).unwrap();
+        assert_eq!(decoded.node_data.len(), 5);
+        assert_eq!(decoded.arc_data.len(), 5);
+        // the old sink 5 is now 4
+        assert_eq!(decoded.arc_endpoints(4), Some((3, 4)));
+    }
+
+    #[test]
+    fn malformed_input() {
+        let (_, _, network) = network_instance1();
+        let bytes = network.to_bytes();
+        assert_eq!(
+            GraphNetwork::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            DecodeError::UnexpectedEnd
+        );
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(
+            GraphNetwork::from_bytes(&trailing).unwrap_err(),
+            DecodeError::TrailingBytes(1)
+        );
+
+        // one node, one arc into node 1
+        let out_of_range: Vec<u8> = [1u32, 1, 0, 1, 5, 0]
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .collect();
+        assert_eq!(
+            GraphNetwork::from_bytes(&out_of_range).unwrap_err(),
+            DecodeError::NodeOutOfRange(1)
+        );
+
+        // a node count of 2^32 - 1 would be allocated before any arc is read
+        let too_many: Vec<u8> = [u32::MAX, 0].iter().flat_map(|n| n.to_le_bytes()).collect();
+        assert_eq!(
+            GraphNetwork::from_bytes(&too_many).unwrap_err(),
+            DecodeError::TooManyNodes(u32::MAX as usize)
+        );
+
+        // two nodes, a fitting arc and one carrying 6 of 5
+        let over_capacity: Vec<u8> = [2u32, 2, 0, 1, 5, 5, 0, 1, 5, 6]
+            .iter()
+            .flat_map(|n| n.to_le_bytes())
+            .collect();
+        assert_eq!(
+            GraphNetwork::from_bytes(&over_capacity).unwrap_err(),
+            DecodeError::FlowExceedsCapacity(1)
+        );
+    }
+}
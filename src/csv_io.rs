@@ -0,0 +1,119 @@
+use std::fmt;
+use std::io::{self, BufRead};
+
+use crate::dimacs::MAX_NODES;
+use crate::graph::{GraphNetwork, NodeId};
+use crate::karzanov::{KarzanovArc, KarzanovNode};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsvError {
+    /// reading the input failed
+    Io(io::ErrorKind),
+    /// the line of this number has a field which is not a number
+    NonNumeric(usize),
+    /// the line of this number does not have exactly `from,into,capacity`
+    FieldCount(usize),
+    /// the line of this number refers to a node id of `MAX_NODES` or more, as every node up to it is allocated
+    IdTooLarge(usize),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(kind) => write!(f, "cannot read the input: {}", kind),
+            CsvError::NonNumeric(line) => write!(f, "line {} has a non-numeric field", line),
+            CsvError::FieldCount(line) => {
+                write!(f, "line {} does not have three fields", line)
+            }
+            CsvError::IdTooLarge(line) => write!(f, "line {} has a node id too large", line),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+/// read an edge list of `from,into,capacity` lines, creating the nodes up to the largest id, which stays below `MAX_NODES`.
+/// blank lines are ignored, and so is a first line none of whose fields is a number, as a header
+pub fn from_csv<R: BufRead>(r: R) -> Result<GraphNetwork<KarzanovNode, KarzanovArc>, CsvError> {
+    let mut arcs: Vec<(NodeId, NodeId, u32)> = Vec::new();
+    let mut node_count = 0;
+    let mut is_first = true;
+    for (i, text) in r.lines().enumerate() {
+        let text = text.map_err(|error| CsvError::Io(error.kind()))?;
+        let line = i + 1;
+        if text.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = text.split(',').map(str::trim).collect();
+        if fields.len() != 3 {
+            return Err(CsvError::FieldCount(line));
+        }
+        let parsed = (
+            fields[0].parse::<NodeId>(),
+            fields[1].parse::<NodeId>(),
+            fields[2].parse::<u32>(),
+        );
+        match parsed {
+            (Ok(from), Ok(into), Ok(capacity)) => {
+                match from.max(into).checked_add(1) {
+                    Some(end) if end <= MAX_NODES => node_count = node_count.max(end),
+                    _ => return Err(CsvError::IdTooLarge(line)),
+                }
+                arcs.push((from, into, capacity));
+            }
+            // a line with a number in it is a row with a typo, not a header
+            _ if is_first && fields.iter().all(|field| field.parse::<f64>().is_err()) => {}
+            _ => return Err(CsvError::NonNumeric(line)),
+        }
+        is_first = false;
+    }
+
+    let mut network = GraphNetwork::new();
+    network.add_nodes(vec![KarzanovNode::new(); node_count].into_iter());
+    network.bulk_connect(
+        arcs.into_iter()
+            .map(|(from, into, capacity)| (from, into, KarzanovArc::new(capacity))),
+    );
+    Ok(network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::karzanov::maxflow;
+
+    #[test]
+    fn from_csv() {
+        let input = "from,into,capacity\n0,1,2\n\n1,2,1\n1,2,3\n";
+        let mut network = super::from_csv(input.as_bytes()).unwrap();
+        assert_eq!(network.node_data.len(), 3);
+        assert_eq!(network.arc_data.len(), 3);
+        assert_eq!(network.arc_endpoints(2), Some((1, 2)));
+        assert_eq!(maxflow(0, 2, &mut network), Ok(2));
+
+        assert_eq!(
+            super::from_csv("0,1,2\n1,x,1\n".as_bytes()).map(|_| ()),
+            Err(CsvError::NonNumeric(2))
+        );
+        assert_eq!(
+            super::from_csv("0,1,x\n0,1,3\n".as_bytes()).map(|_| ()),
+            Err(CsvError::NonNumeric(1))
+        );
+        assert_eq!(
+            super::from_csv("a,b,-1\n0,1,3\n".as_bytes()).map(|_| ()),
+            Err(CsvError::NonNumeric(1))
+        );
+        assert_eq!(
+            super::from_csv("0,1\n".as_bytes()).map(|_| ()),
+            Err(CsvError::FieldCount(1))
+        );
+
+        // every node up to the largest id would be allocated
+        for input in ["18446744073709551615,0,1\n", "0,1000000000000,1\n"] {
+            assert_eq!(
+                super::from_csv(input.as_bytes()).map(|_| ()),
+                Err(CsvError::IdTooLarge(1))
+            );
+        }
+    }
+}
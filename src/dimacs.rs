@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+use crate::graph::{GraphNetwork, NodeId};
+use crate::karzanov::{KarzanovArc, KarzanovNode};
+
+/// the most nodes a problem may declare, as they are all allocated up front
+pub(crate) const MAX_NODES: usize = 1 << 24;
+/// the most arcs reserved up front, so that a huge arc count in the problem line allocates nothing
+const MAX_RESERVED_ARCS: usize = 1 << 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DimacsError {
+    /// reading the input failed
+    Io(io::ErrorKind),
+    /// the line of this number does not parse
+    MalformedLine(usize),
+    /// the line of this number refers to a node beyond the node count
+    NodeOutOfRange(usize),
+    /// there is no `p max` line before the nodes and the arcs
+    MissingProblem,
+    /// the `p max` line of this number declares more nodes than `MAX_NODES`
+    TooManyNodes(usize),
+    /// there is no `n id s` line
+    MissingSource,
+    /// there is no `n id t` line
+    MissingSink,
+}
+
+impl fmt::Display for DimacsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DimacsError::Io(kind) => write!(f, "cannot read the input: {}", kind),
+            DimacsError::MalformedLine(line) => write!(f, "line {} is malformed", line),
+            DimacsError::NodeOutOfRange(line) => {
+                write!(f, "line {} refers to a node out of range", line)
+            }
+            DimacsError::MissingProblem => write!(f, "no `p max` line"),
+            DimacsError::TooManyNodes(line) => {
+                write!(f, "line {} declares too many nodes", line)
+            }
+            DimacsError::MissingSource => write!(f, "no source line"),
+            DimacsError::MissingSink => write!(f, "no sink line"),
+        }
+    }
+}
+
+impl std::error::Error for DimacsError {}
+
+/// parse a 1-based node id of a `node_count` nodes problem into a 0-based one
+fn parse_node(word: Option<&str>, node_count: usize, line: usize) -> Result<NodeId, DimacsError> {
+    let id: usize = word
+        .and_then(|word| word.parse().ok())
+        .ok_or(DimacsError::MalformedLine(line))?;
+    if id == 0 || id > node_count {
+        return Err(DimacsError::NodeOutOfRange(line));
+    }
+    Ok(id - 1)
+}
+
+/// read a max-flow problem in the DIMACS format as the source, the sink and the network.
+/// the ids are 1-based in the input and 0-based in the network
+pub fn read_dimacs<R: BufRead>(
+    r: R,
+) -> Result<(NodeId, NodeId, GraphNetwork<KarzanovNode, KarzanovArc>), DimacsError> {
+    let mut node_count: Option<usize> = None;
+    let (mut source_id, mut sink_id) = (None, None);
+    let mut arcs = Vec::new();
+    for (i, text) in r.lines().enumerate() {
+        let text = text.map_err(|error| DimacsError::Io(error.kind()))?;
+        let line = i + 1;
+        let mut words = text.split_whitespace();
+        match words.next() {
+            // blank lines and comments
+            None | Some("c") => continue,
+            Some("p") => {
+                if node_count.is_some() || words.next() != Some("max") {
+                    return Err(DimacsError::MalformedLine(line));
+                }
+                let count = words.next().and_then(|word| word.parse().ok());
+                let arc_count = words.next().and_then(|word| word.parse::<usize>().ok());
+                match (count, arc_count) {
+                    (Some(count), _) if count > MAX_NODES => {
+                        return Err(DimacsError::TooManyNodes(line))
+                    }
+                    (Some(count), Some(arc_count)) => {
+                        node_count = Some(count);
+                        arcs.reserve(arc_count.min(MAX_RESERVED_ARCS));
+                    }
+                    _ => return Err(DimacsError::MalformedLine(line)),
+                }
+            }
+            Some("n") => {
+                let node_count = node_count.ok_or(DimacsError::MissingProblem)?;
+                let node_id = parse_node(words.next(), node_count, line)?;
+                match words.next() {
+                    Some("s") => source_id = Some(node_id),
+                    Some("t") => sink_id = Some(node_id),
+                    _ => return Err(DimacsError::MalformedLine(line)),
+                }
+            }
+            Some("a") => {
+                let node_count = node_count.ok_or(DimacsError::MissingProblem)?;
+                let from = parse_node(words.next(), node_count, line)?;
+                let into = parse_node(words.next(), node_count, line)?;
+                let capacity: u32 = words
+                    .next()
+                    .and_then(|word| word.parse().ok())
+                    .ok_or(DimacsError::MalformedLine(line))?;
+                arcs.push((from, into, KarzanovArc::new(capacity)));
+            }
+            Some(_) => return Err(DimacsError::MalformedLine(line)),
+        }
+    }
+
+    let node_count = node_count.ok_or(DimacsError::MissingProblem)?;
+    let source_id = source_id.ok_or(DimacsError::MissingSource)?;
+    let sink_id = sink_id.ok_or(DimacsError::MissingSink)?;
+    let mut network = GraphNetwork::new();
+    network.add_nodes(vec![KarzanovNode::new(); node_count].into_iter());
+    network.bulk_connect(arcs.into_iter());
+    Ok((source_id, sink_id, network))
+}
+
+/// write the network as a max-flow problem in the DIMACS format, with 1-based ids.
+/// removed nodes and arcs are skipped and the remaining ids compacted, as `read_dimacs` expects.
+/// fails with `InvalidInput` if the source or the sink is not in the network
+pub fn write_dimacs<W: Write>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+    w: &mut W,
+) -> io::Result<()> {
+    let mut old_new_map = HashMap::<NodeId, NodeId>::new();
+    for (old_node_id, node) in network.node_data.iter().enumerate() {
+        if node.is_some() {
+            old_new_map.insert(old_node_id, old_new_map.len() + 1);
+        }
+    }
+    let (Some(new_source_id), Some(new_sink_id)) =
+        (old_new_map.get(&source_id), old_new_map.get(&sink_id))
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the source or the sink is not in the network",
+        ));
+    };
+    let arcs: Vec<(NodeId, NodeId, u32)> = (0..network.arc_data.len())
+        .filter_map(|arc_id| {
+            let (from, into) = network.arc_endpoints(arc_id)?;
+            let arc = network.data_of_arc(arc_id)?;
+            Some((old_new_map[&from], old_new_map[&into], arc.capacity))
+        })
+        .collect();
+
+    writeln!(w, "p max {} {}", old_new_map.len(), arcs.len())?;
+    writeln!(w, "n {} s", new_source_id)?;
+    writeln!(w, "n {} t", new_sink_id)?;
+    for (from, into, capacity) in arcs {
+        writeln!(w, "a {} {} {}", from, into, capacity)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instances::network_instance2;
+    use crate::karzanov::maxflow;
+
+    #[test]
+    fn read_dimacs() {
+        let input = "c network_instance3\n\
+                     p max 3 2\n\
+                     n 1 s\n\
+                     n 3 t\n\
+                     a 1 2 1\n\
+                     a 2 3 2\n";
+        let (source, sink, mut network) = super::read_dimacs(input.as_bytes()).unwrap();
+        assert_eq!((source, sink), (0, 2));
+        assert_eq!(maxflow(source, sink, &mut network), Ok(1));
+    }
+
+    #[test]
+    fn malformed_dimacs() {
+        let read = |input: &str| super::read_dimacs(input.as_bytes()).map(|_| ());
+        assert_eq!(read("a 1 2 1\n"), Err(DimacsError::MissingProblem));
+        assert_eq!(
+            read("p max 2 1\nn 1 s\na 1 2 1\n"),
+            Err(DimacsError::MissingSink)
+        );
+        assert_eq!(read("p max 2 1\nn 2 t\n"), Err(DimacsError::MissingSource));
+        assert_eq!(
+            read("p max 2 1\nn 1 s\na 1 3 1\n"),
+            Err(DimacsError::NodeOutOfRange(3))
+        );
+        assert_eq!(
+            read("p max 2 1\na 1 2 x\n"),
+            Err(DimacsError::MalformedLine(2))
+        );
+        assert_eq!(read("p min 2 1\n"), Err(DimacsError::MalformedLine(1)));
+    }
+
+    #[test]
+    fn oversized_dimacs_header() {
+        let read = |input: &str| super::read_dimacs(input.as_bytes()).map(|_| ());
+        // the arc count is only a hint
+        assert_eq!(read("p max 2 18446744073709551615\nn 1 s\nn 2 t\n"), Ok(()));
+        assert_eq!(
+            read("p max 18446744073709551615 1\n"),
+            Err(DimacsError::TooManyNodes(1))
+        );
+        assert_eq!(
+            read("c huge\np max 99999999 0\n"),
+            Err(DimacsError::TooManyNodes(2))
+        );
+    }
+
+    #[test]
+    fn write_then_read_dimacs() {
+        let (source, sink, mut network) = network_instance2();
+        // leave a hole before an isolated node, so that the ids get compacted
+        let node_id = network.add_node(KarzanovNode::new());
+        network.add_node(KarzanovNode::new());
+        network.remove_node(node_id);
+        let expected = maxflow(source, sink, &mut network.clone()).unwrap();
+
+        let mut written = Vec::new();
+        write_dimacs(source, sink, &network, &mut written).unwrap();
+        let (source, sink, mut network) = super::read_dimacs(written.as_slice()).unwrap();
+        assert_eq!(network.node_data.len(), 10);
+        assert_eq!(maxflow(source, sink, &mut network), Ok(expected));
+
+        let mut rewritten = Vec::new();
+        write_dimacs(source, sink, &network, &mut rewritten).unwrap();
+        assert_eq!(rewritten, written);
+    }
+
+    #[test]
+    fn write_dimacs_without_the_sink() {
+        let (source, sink, mut network) = network_instance2();
+        network.remove_node(sink);
+        let mut written = Vec::new();
+        let error = write_dimacs(source, sink, &network, &mut written).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert!(written.is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+
+use crate::graph::{GraphNetwork, NodeId};
+use crate::karzanov::{KarzanovArc, KarzanovNode};
+
+/// an arc of the residual network. `twin` is the index of the opposite arc
+struct ResidualArc {
+    into: NodeId,
+    residual: u32,
+    twin: usize,
+}
+
+/// every arc of the network as a forward residual arc and its backward twin,
+/// numbered `2 * arc_id` and `2 * arc_id + 1`
+struct Residual {
+    arcs: Vec<ResidualArc>,
+    arcs_from: Vec<Vec<usize>>,
+}
+
+impl Residual {
+    fn new(network: &GraphNetwork<KarzanovNode, KarzanovArc>) -> Self {
+        let mut residual = Residual {
+            arcs: Vec::new(),
+            arcs_from: vec![Vec::new(); network.node_data.len()],
+        };
+        for arc_id in 0..network.arc_data.len() {
+            // a removed arc stays as a pair of arcs of no capacity, so that the numbering holds
+            let (from, into, capacity) = match network.arc_endpoints(arc_id) {
+                Some((from, into)) => (from, into, network.data_of_arc(arc_id).unwrap().capacity),
+                None => (0, 0, 0),
+            };
+            if capacity > 0 {
+                residual.arcs_from[from].push(2 * arc_id);
+                residual.arcs_from[into].push(2 * arc_id + 1);
+            }
+            residual.arcs.push(ResidualArc {
+                into,
+                residual: capacity,
+                twin: 2 * arc_id + 1,
+            });
+            residual.arcs.push(ResidualArc {
+                into: from,
+                residual: 0,
+                twin: 2 * arc_id,
+            });
+        }
+        residual
+    }
+
+    /// the distance of every node from the source over arcs with residual capacity
+    fn levels(&self, source_id: NodeId) -> Vec<Option<usize>> {
+        let mut levels = vec![None; self.arcs_from.len()];
+        levels[source_id] = Some(0);
+        let mut queue = VecDeque::from([source_id]);
+        while let Some(node_id) = queue.pop_front() {
+            for index in &self.arcs_from[node_id] {
+                let arc = &self.arcs[*index];
+                if arc.residual > 0 && levels[arc.into].is_none() {
+                    levels[arc.into] = levels[node_id].map(|level| level + 1);
+                    queue.push_back(arc.into);
+                }
+            }
+        }
+        levels
+    }
+
+    /// push up to `limit` from `node_id` to the sink along the level graph, and return how much went through.
+    /// `next` keeps the arcs already found useless in this phase out of the later searches
+    fn push(
+        &mut self,
+        node_id: NodeId,
+        sink_id: NodeId,
+        limit: u32,
+        levels: &[Option<usize>],
+        next: &mut [usize],
+    ) -> u32 {
+        if node_id == sink_id {
+            return limit;
+        }
+        while next[node_id] < self.arcs_from[node_id].len() {
+            let index = self.arcs_from[node_id][next[node_id]];
+            let (into, residual) = (self.arcs[index].into, self.arcs[index].residual);
+            let is_level_arc =
+                levels[into].is_some() && levels[into] == levels[node_id].map(|l| l + 1);
+            if residual > 0 && is_level_arc {
+                let pushed = self.push(into, sink_id, limit.min(residual), levels, next);
+                if pushed > 0 {
+                    self.arcs[index].residual -= pushed;
+                    let twin = self.arcs[index].twin;
+                    self.arcs[twin].residual += pushed;
+                    return pushed;
+                }
+            }
+            next[node_id] += 1;
+        }
+        0
+    }
+}
+
+/// solve the network with Dinic's algorithm, overwriting the flows, and return the flow value.
+/// it keeps its own residual network and accepts any graph, so it serves as an oracle for karzanov's algorithm
+pub fn maxflow(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> u32 {
+    let mut residual = Residual::new(network);
+    let mut flow_value = 0;
+    if source_id != sink_id {
+        loop {
+            let levels = residual.levels(source_id);
+            if levels[sink_id].is_none() {
+                break;
+            }
+            let mut next = vec![0; network.node_data.len()];
+            loop {
+                let pushed = residual.push(source_id, sink_id, u32::MAX, &levels, &mut next);
+                if pushed == 0 {
+                    break;
+                }
+                flow_value += pushed;
+            }
+        }
+    }
+    for (arc_id, arc) in network.arc_data.iter_mut().enumerate() {
+        if let Some(arc) = arc {
+            // the backward twin holds what the forward arc carries
+            arc.flow = residual.arcs[2 * arc_id + 1].residual;
+        }
+    }
+    flow_value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen::random_layered;
+    use crate::instances::*;
+    use crate::karzanov;
+
+    #[test]
+    fn agrees_with_karzanov() {
+        for (source, sink, network) in [
+            network_instance1(),
+            network_instance2(),
+            network_instance3(),
+            network_instance4(),
+        ] {
+            let mut by_dinic = network.clone();
+            let mut by_karzanov = network;
+            assert_eq!(
+                karzanov::maxflow(source, sink, &mut by_karzanov),
+                Ok(maxflow(source, sink, &mut by_dinic))
+            );
+            assert!(by_dinic.all_nodes_balanced(source, sink));
+        }
+
+        for seed in 0..500 {
+            let layers = 1 + seed as usize % 5;
+            let width = 1 + seed as usize / 5 % 5;
+            let (source, sink, network) = random_layered(layers, width, 10, seed);
+            let mut by_dinic = network.clone();
+            let mut by_karzanov = network;
+            assert_eq!(
+                karzanov::maxflow(source, sink, &mut by_karzanov),
+                Ok(maxflow(source, sink, &mut by_dinic)),
+                "seed {}",
+                seed
+            );
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use crate::graph::{ArcId, GraphNetwork, NodeId};
+use crate::karzanov::{self, KarzanovArc, KarzanovNode, MaxFlowError};
+
+/// the result of solving the same network with Karzanov's algorithm and Edmonds-Karp
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrategyComparison {
+    /// the max-flow value, which both solvers agree on
+    pub flow_value: u32,
+    /// `(arc, karzanov flow, edmonds-karp flow)` of the arcs whose flows differ.
+    /// maximum flows are not unique, so this may be non-empty even for correct solvers
+    pub differences: Vec<(ArcId, u32, u32)>,
+}
+
+/// find a shortest path in the residual network.
+/// each step is an arc and whether it is used forward (`true`) or cancelled backward (`false`)
+fn shortest_augmenting_path(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Option<Vec<(ArcId, bool)>> {
+    let mut parent: Vec<Option<(NodeId, ArcId, bool)>> = vec![None; network.node_data.len()];
+    let mut reached = vec![false; network.node_data.len()];
+    reached[source_id] = true;
+    let mut queue = VecDeque::from([source_id]);
+    while let Some(node_id) = queue.pop_front() {
+        if node_id == sink_id {
+            break;
+        }
+        let forward = network.from_node(node_id).filter(|(_, arc_id)| {
+            let arc = network.data_of_arc(*arc_id).unwrap();
+            arc.flow < arc.capacity
+        });
+        let backward = network
+            .into_node(node_id)
+            .filter(|(_, arc_id)| network.data_of_arc(*arc_id).unwrap().flow > 0);
+        let steps = forward
+            .map(|(next_id, arc_id)| (next_id, arc_id, true))
+            .chain(backward.map(|(next_id, arc_id)| (next_id, arc_id, false)));
+        for (next_id, arc_id, is_forward) in steps {
+            if reached[next_id] {
+                continue;
+            }
+            reached[next_id] = true;
+            parent[next_id] = Some((node_id, arc_id, is_forward));
+            queue.push_back(next_id);
+        }
+    }
+    if !reached[sink_id] {
+        return None;
+    }
+
+    let mut path = Vec::new();
+    let mut node_id = sink_id;
+    while let Some((prev_id, arc_id, is_forward)) = parent[node_id] {
+        path.push((arc_id, is_forward));
+        node_id = prev_id;
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// solve the network by augmenting along shortest residual paths, overwriting the flows.
+/// unlike karzanov's algorithm, it accepts any graph
+pub fn maxflow(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> u32 {
+    for arc in network.arc_data.iter_mut().flatten() {
+        arc.flow = 0;
+    }
+    if source_id == sink_id {
+        return 0;
+    }
+    let mut flow_value = 0;
+    while let Some(path) = shortest_augmenting_path(source_id, sink_id, network) {
+        let bottleneck = path
+            .iter()
+            .map(|(arc_id, is_forward)| {
+                let arc = network.data_of_arc(*arc_id).unwrap();
+                if *is_forward {
+                    arc.capacity - arc.flow
+                } else {
+                    arc.flow
+                }
+            })
+            .min()
+            .unwrap();
+        for (arc_id, is_forward) in path {
+            let arc = network.mut_data_of_arc(arc_id).unwrap();
+            if is_forward {
+                arc.flow += bottleneck;
+            } else {
+                arc.flow -= bottleneck;
+            }
+        }
+        flow_value += bottleneck;
+    }
+    flow_value
+}
+
+impl GraphNetwork<KarzanovNode, KarzanovArc> {
+    /// solve clones of the network with both karzanov's algorithm and Edmonds-Karp and compare them.
+    /// panics if their flow values disagree, and fails if the network does not suit karzanov's algorithm
+    pub fn compare_strategies(
+        &self,
+        source_id: NodeId,
+        sink_id: NodeId,
+    ) -> Result<StrategyComparison, MaxFlowError> {
+        let mut by_karzanov = self.clone();
+        let karzanov_value = karzanov::maxflow(source_id, sink_id, &mut by_karzanov)?;
+
+        let mut by_edmonds_karp = self.clone();
+        let edmonds_karp_value = maxflow(source_id, sink_id, &mut by_edmonds_karp);
+        assert_eq!(
+            karzanov_value, edmonds_karp_value,
+            "karzanov's algorithm and Edmonds-Karp disagree on the flow value"
+        );
+
+        let mut differences = Vec::new();
+        for (arc_id, (arc, other)) in by_karzanov
+            .arc_data
+            .iter()
+            .zip(&by_edmonds_karp.arc_data)
+            .enumerate()
+        {
+            if let (Some(arc), Some(other)) = (arc, other) {
+                if arc.flow != other.flow {
+                    differences.push((arc_id, arc.flow, other.flow));
+                }
+            }
+        }
+        Ok(StrategyComparison {
+            flow_value: karzanov_value,
+            differences,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instances::*;
+
+    #[test]
+    fn edmonds_karp() {
+        let (source, sink, mut network) = network_instance2();
+        assert_eq!(maxflow(source, sink, &mut network), 6);
+        assert_eq!(karzanov::flow_value(source, &network), 6);
+    }
+
+    #[test]
+    fn compare_on_sample_instances() {
+        let instances = [
+            (network_instance1(), 5),
+            (network_instance2(), 6),
+            (network_instance3(), 1),
+            (network_instance4(), 1),
+        ];
+        for ((source, sink, network), expected) in instances {
+            let comparison = network.compare_strategies(source, sink).unwrap();
+            assert_eq!(comparison.flow_value, expected);
+            for (arc_id, _, _) in comparison.differences {
+                assert!(network.data_of_arc(arc_id).is_some());
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::graph::{ArcId, GraphNetwork, NodeId};
+use crate::karzanov::{KarzanovArc, KarzanovNode};
+
+impl GraphNetwork<KarzanovNode, KarzanovArc> {
+    /// a mermaid `graph LR` flowchart with the arcs labeled `flow/capacity`.
+    /// the source and the sink are drawn round and the saturated arcs thick, for markdown docs
+    pub fn to_mermaid(&self, source_id: NodeId, sink_id: NodeId) -> String {
+        let mut mermaid = String::from("graph LR\n");
+        for node_id in (0..self.node_data.len()).filter(|node_id| self.is_node_in(*node_id)) {
+            if node_id == source_id || node_id == sink_id {
+                writeln!(mermaid, "    n{}(({}))", node_id, node_id).unwrap();
+            } else {
+                writeln!(mermaid, "    n{}[{}]", node_id, node_id).unwrap();
+            }
+        }
+        // mermaid styles the links by their order of appearance
+        let mut saturated = Vec::new();
+        let mut link_index = 0;
+        for arc_id in 0..self.arc_data.len() {
+            let Some((from, into)) = self.arc_endpoints(arc_id) else {
+                continue;
+            };
+            let arc = self.data_of_arc(arc_id).unwrap();
+            writeln!(
+                mermaid,
+                "    n{} -->|{}/{}| n{}",
+                from, arc.flow, arc.capacity, into
+            )
+            .unwrap();
+            if arc.capacity > 0 && arc.flow == arc.capacity {
+                saturated.push(link_index.to_string());
+            }
+            link_index += 1;
+        }
+        if !saturated.is_empty() {
+            writeln!(
+                mermaid,
+                "    linkStyle {} stroke-width:3px",
+                saturated.join(",")
+            )
+            .unwrap();
+        }
+        mermaid
+    }
+
+    /// a graphviz `digraph` with the arcs labeled `flow/capacity`.
+    /// the source and the sink are filled with colors and the saturated arcs drawn bold
+    pub fn to_dot(&self, source_id: NodeId, sink_id: NodeId) -> String {
+        let mut dot = String::from("digraph {\n");
+        for node_id in (0..self.node_data.len()).filter(|node_id| self.is_node_in(*node_id)) {
+            if node_id == source_id {
+                writeln!(dot, "    {} [style=filled, fillcolor=lightblue];", node_id).unwrap();
+            } else if node_id == sink_id {
+                writeln!(dot, "    {} [style=filled, fillcolor=salmon];", node_id).unwrap();
+            } else {
+                writeln!(dot, "    {};", node_id).unwrap();
+            }
+        }
+        for arc_id in 0..self.arc_data.len() {
+            let Some((from, into)) = self.arc_endpoints(arc_id) else {
+                continue;
+            };
+            let arc = self.data_of_arc(arc_id).unwrap();
+            let style = if arc.capacity > 0 && arc.flow == arc.capacity {
+                ", style=bold"
+            } else {
+                ""
+            };
+            writeln!(
+                dot,
+                "    {} -> {} [label=\"{}/{}\"{}];",
+                from, into, arc.flow, arc.capacity, style
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// a json object with the number of nodes and an array of the arcs,
+    /// e.g. `{"nodes":2,"arcs":[{"from":0,"into":1,"capacity":3,"flow":0}]}`.
+    /// the node ids are compacted as in `to_bytes`
+    pub fn to_json(&self) -> String {
+        let mut old_new_map = HashMap::<NodeId, NodeId>::new();
+        for (old_node_id, node) in self.node_data.iter().enumerate() {
+            if node.is_some() {
+                old_new_map.insert(old_node_id, old_new_map.len());
+            }
+        }
+        let arcs: Vec<String> = (0..self.arc_data.len())
+            .filter_map(|arc_id| {
+                let (from, into) = self.arc_endpoints(arc_id)?;
+                let arc = self.data_of_arc(arc_id)?;
+                Some(format!(
+                    r#"{{"from":{},"into":{},"capacity":{},"flow":{}}}"#,
+                    old_new_map[&from], old_new_map[&into], arc.capacity, arc.flow
+                ))
+            })
+            .collect();
+        format!(
+            r#"{{"nodes":{},"arcs":[{}]}}"#,
+            old_new_map.len(),
+            arcs.join(",")
+        )
+    }
+}
+
+/// every live arc as `from -> into: flow/capacity`, then every live node with its net inflow,
+/// e.g. `node 1: +1`, which is 0 for every node but the source and the sink of a solved network
+impl std::fmt::Display for GraphNetwork<KarzanovNode, KarzanovArc> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for arc_id in 0..self.arc_data.len() {
+            if let (Some((from, into)), Some(arc)) =
+                (self.arc_endpoints(arc_id), self.data_of_arc(arc_id))
+            {
+                writeln!(f, "{} -> {}: {}/{}", from, into, arc.flow, arc.capacity)?;
+            }
+        }
+        for node_id in (0..self.node_data.len()).filter(|node_id| self.is_node_in(*node_id)) {
+            let flow_of =
+                |(_, arc_id): (NodeId, ArcId)| self.data_of_arc(arc_id).unwrap().flow as i64;
+            let balance = self.into_node(node_id).map(flow_of).sum::<i64>()
+                - self.from_node(node_id).map(flow_of).sum::<i64>();
+            writeln!(f, "node {}: {:+}", node_id, balance)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::instances::{network_instance1, network_instance3, network_instance4};
+    use crate::karzanov::maxflow;
+
+    #[test]
+    fn to_mermaid() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        let mermaid = network.to_mermaid(source, sink);
+        assert!(mermaid.starts_with("graph LR\n"));
+        assert!(mermaid.contains("\n    n0 -->|2/2| n1\n"));
+        assert!(mermaid.contains("n0((0))"));
+        assert!(mermaid.contains("linkStyle "));
+    }
+
+    #[test]
+    fn to_json() {
+        let (_, _, mut network) = network_instance3();
+        network.mut_data_of_arc(0).unwrap().flow = 1;
+        let json = network.to_json();
+        // the brackets are balanced and never close early
+        let mut depth = 0;
+        for (i, c) in json.char_indices() {
+            match c {
+                '{' | '[' => depth += 1,
+                '}' | ']' => depth -= 1,
+                _ => {}
+            }
+            assert!(depth > 0 || i == json.len() - 1);
+        }
+        assert_eq!(depth, 0);
+        assert!(json.starts_with(r#"{"nodes":3,"arcs":["#));
+        assert_eq!(json.matches(r#""from":"#).count(), 2);
+        assert!(json.contains(r#"{"from":0,"into":1,"capacity":1,"flow":1}"#));
+    }
+
+    #[test]
+    fn to_dot() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        network.disconnect(3);
+        let dot = network.to_dot(source, sink);
+        assert!(dot.starts_with("digraph {\n") && dot.ends_with("}\n"));
+        assert_eq!(dot.matches(" -> ").count(), 6);
+        assert!(dot.contains("    0 -> 1 [label=\"2/2\", style=bold];\n"));
+        assert!(dot.contains("    0 [style=filled, fillcolor=lightblue];\n"));
+    }
+
+    #[test]
+    fn display() {
+        let (source, sink, mut network) = network_instance4();
+        assert_eq!(network.to_string(), "0 -> 1: 0/1\nnode 0: +0\nnode 1: +0\n");
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(network.to_string(), "0 -> 1: 1/1\nnode 0: -1\nnode 1: +1\n");
+    }
+}
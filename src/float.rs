@@ -0,0 +1,164 @@
+use std::collections::VecDeque;
+
+use crate::graph::{ArcId, GraphNetwork, NodeId};
+
+/// a tolerance well above the drift of summing a few thousand `f64` capacities,
+/// and well below any meaningful capacity
+pub const DEFAULT_EPSILON: f64 = 1e-9;
+
+/// an arc with a floating-point capacity
+#[derive(Debug, Clone)]
+pub struct FloatArc {
+    capacity: f64,
+    flow: f64,
+}
+
+impl FloatArc {
+    pub fn new(capacity: f64) -> Self {
+        FloatArc {
+            capacity,
+            flow: 0.0,
+        }
+    }
+
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    pub fn flow(&self) -> f64 {
+        self.flow
+    }
+
+    /// an arc within `epsilon` of its capacity counts as saturated
+    fn is_saturated(&self, epsilon: f64) -> bool {
+        self.flow + epsilon >= self.capacity
+    }
+}
+
+/// find a shortest path of residual capacities above `epsilon`.
+/// each step is an arc and whether it is used forward (`true`) or cancelled backward (`false`)
+fn shortest_augmenting_path<N>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<N, FloatArc>,
+    epsilon: f64,
+) -> Option<Vec<(ArcId, bool)>> {
+    let mut parent: Vec<Option<(NodeId, ArcId, bool)>> = vec![None; network.node_data.len()];
+    let mut reached = vec![false; network.node_data.len()];
+    reached[source_id] = true;
+    let mut queue = VecDeque::from([source_id]);
+    while let Some(node_id) = queue.pop_front() {
+        if node_id == sink_id {
+            break;
+        }
+        let forward = network
+            .from_node(node_id)
+            .filter(|(_, arc_id)| !network.data_of_arc(*arc_id).unwrap().is_saturated(epsilon))
+            .map(|(next_id, arc_id)| (next_id, arc_id, true));
+        let backward = network
+            .into_node(node_id)
+            .filter(|(_, arc_id)| network.data_of_arc(*arc_id).unwrap().flow > epsilon)
+            .map(|(next_id, arc_id)| (next_id, arc_id, false));
+        for (next_id, arc_id, is_forward) in forward.chain(backward) {
+            if !reached[next_id] {
+                reached[next_id] = true;
+                parent[next_id] = Some((node_id, arc_id, is_forward));
+                queue.push_back(next_id);
+            }
+        }
+    }
+    if !reached[sink_id] {
+        return None;
+    }
+
+    let mut path = Vec::new();
+    let mut node_id = sink_id;
+    while let Some((prev_id, arc_id, is_forward)) = parent[node_id] {
+        path.push((arc_id, is_forward));
+        node_id = prev_id;
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// solve a network of floating-point capacities, overwriting the flows, and return the flow value.
+/// residuals within `epsilon` count as zero, so the drift of floating-point sums can neither
+/// keep a saturated arc open nor make the search loop forever. `DEFAULT_EPSILON` suits most inputs
+pub fn maxflow_with_tolerance<N>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<N, FloatArc>,
+    epsilon: f64,
+) -> f64 {
+    for arc in network.arc_data.iter_mut().flatten() {
+        arc.flow = 0.0;
+    }
+    if source_id == sink_id {
+        return 0.0;
+    }
+    let mut flow_value = 0.0;
+    while let Some(path) = shortest_augmenting_path(source_id, sink_id, network, epsilon) {
+        let bottleneck = path
+            .iter()
+            .map(|(arc_id, is_forward)| {
+                let arc = network.data_of_arc(*arc_id).unwrap();
+                if *is_forward {
+                    arc.capacity - arc.flow
+                } else {
+                    arc.flow
+                }
+            })
+            .fold(f64::INFINITY, f64::min);
+        for (arc_id, is_forward) in path {
+            let arc = network.mut_data_of_arc(arc_id).unwrap();
+            if is_forward {
+                // never leave the arc a hair above its capacity
+                arc.flow = (arc.flow + bottleneck).min(arc.capacity);
+            } else {
+                arc.flow = (arc.flow - bottleneck).max(0.0);
+            }
+        }
+        flow_value += bottleneck;
+    }
+    flow_value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenths() {
+        // ten arcs of 0.1 into a node which passes on exactly 1.0
+        let mut network = GraphNetwork::<(), FloatArc>::new();
+        network.add_nodes(vec![(); 3].into_iter());
+        for _ in 0..10 {
+            network.connect(0, 1, FloatArc::new(0.1));
+        }
+        network.connect(1, 2, FloatArc::new(1.0));
+        let flow_value = maxflow_with_tolerance(0, 2, &mut network, DEFAULT_EPSILON);
+        assert!((flow_value - 1.0).abs() < DEFAULT_EPSILON);
+        assert!(network
+            .data_of_arc(10)
+            .unwrap()
+            .is_saturated(DEFAULT_EPSILON));
+
+        // 0.1 + 0.2 through a bottleneck of 0.3
+        let mut network = GraphNetwork::<(), FloatArc>::new();
+        network.add_nodes(vec![(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, FloatArc::new(0.1)),
+                (0, 2, FloatArc::new(0.2)),
+                (1, 3, FloatArc::new(0.3)),
+                (2, 3, FloatArc::new(0.3)),
+            ]
+            .into_iter(),
+        );
+        let flow_value = maxflow_with_tolerance(0, 3, &mut network, DEFAULT_EPSILON);
+        assert!((flow_value - 0.3).abs() < DEFAULT_EPSILON);
+        for arc in network.arc_data.iter().flatten() {
+            assert!(arc.flow() <= arc.capacity());
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use crate::graph::{GraphNetwork, NodeId};
+use crate::karzanov::{maxflow_general, KarzanovArc, KarzanovNode, MaxFlowError};
+
+// keep the networks small so that a fuzzer runs many of them
+const MAX_INNER_LAYERS: usize = 4;
+const MAX_WIDTH: usize = 4;
+
+/// the input bytes, followed by as many zeros as needed
+struct ByteStream<'b> {
+    bytes: std::slice::Iter<'b, u8>,
+}
+
+impl ByteStream<'_> {
+    fn next(&mut self) -> u8 {
+        self.bytes.next().copied().unwrap_or(0)
+    }
+}
+
+/// connect every node of `from_layer` to the nodes of `into_layer` as the bytes say.
+/// an odd byte makes an arc with the capacity of the remaining bits,
+/// while the first node of `from_layer` always connects, so that every node is reachable from the source
+fn connect_layers(
+    from_layer: &[NodeId],
+    into_layer: &[NodeId],
+    bytes: &mut ByteStream,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+) {
+    for (i, from) in from_layer.iter().enumerate() {
+        for into in into_layer {
+            let byte = bytes.next();
+            if i == 0 || byte & 1 == 1 {
+                network.connect(*from, *into, KarzanovArc::new((byte >> 1) as u32));
+            }
+        }
+    }
+}
+
+/// decode arbitrary bytes into a small layered network and solve it, returning the flow value.
+/// arcs only join consecutive layers and the sink is alone in the last one,
+/// so any input meets the preconditions of karzanov's algorithm. meant as a `cargo fuzz` target,
+/// which should unwrap the result, as any error is a bug of the solver
+pub fn solve_from_bytes(data: &[u8]) -> Result<u32, MaxFlowError> {
+    let mut bytes = ByteStream { bytes: data.iter() };
+    let mut network = GraphNetwork::new();
+    let source_id = network.add_node(KarzanovNode::new());
+
+    let inner_layers = 1 + bytes.next() as usize % MAX_INNER_LAYERS;
+    let mut last_layer = vec![source_id];
+    for _ in 0..inner_layers {
+        let width = 1 + bytes.next() as usize % MAX_WIDTH;
+        let layer: Vec<NodeId> = (0..width)
+            .map(|_| network.add_node(KarzanovNode::new()))
+            .collect();
+        connect_layers(&last_layer, &layer, &mut bytes, &mut network);
+        last_layer = layer;
+    }
+    let sink_id = network.add_node(KarzanovNode::new());
+    connect_layers(&last_layer, &[sink_id], &mut bytes, &mut network);
+
+    // zero-capacity arcs may leave the sink out of reach, which `maxflow_general` takes as no flow
+    maxflow_general(source_id, sink_id, &mut network)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_inputs() {
+        // a chain of zero-capacity arcs
+        assert_eq!(solve_from_bytes(&[]), Ok(0));
+        // one inner node: 0 -(1)-> 1 -(2)-> 2
+        assert_eq!(solve_from_bytes(&[0, 0, 3, 5]), Ok(1));
+        // two inner nodes, each on a path of capacity 3
+        assert_eq!(solve_from_bytes(&[0, 1, 7, 7, 7, 7]), Ok(6));
+        // more bytes than the network needs
+        assert_eq!(solve_from_bytes(&[0, 0, 3, 5, 255, 255]), Ok(1));
+    }
+
+    #[test]
+    fn never_fails() {
+        for seed in 0..=255u8 {
+            let data: Vec<u8> = (0..32u8)
+                .map(|i| seed.wrapping_mul(31).wrapping_add(i.wrapping_mul(seed | 1)))
+                .collect();
+            assert!(solve_from_bytes(&data).is_ok(), "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::graph::{GraphNetwork, NodeId};
+use crate::karzanov::{KarzanovArc, KarzanovNode};
+use crate::utils::XorShift;
+
+/// a random network of `layers` inner layers of `width` nodes between the source and the sink,
+/// with capacities from 1 to `max_cap`. arcs only join consecutive layers, each node gets at least one
+/// arc from the layer before it, and the sink is alone in the last layer, so karzanov's algorithm accepts it.
+/// the same seed always gives the same network
+pub fn random_layered(
+    layers: usize,
+    width: usize,
+    max_cap: u32,
+    seed: u64,
+) -> (NodeId, NodeId, GraphNetwork<KarzanovNode, KarzanovArc>) {
+    let mut rng = XorShift::new(seed);
+    let mut capacity = || (rng.next() % max_cap.max(1) as u64) as u32 + 1;
+    let mut network = GraphNetwork::new();
+    let source_id = network.add_node(KarzanovNode::new());
+
+    let mut last_layer = vec![source_id];
+    for _ in 0..layers {
+        let layer: Vec<NodeId> = (0..width.max(1))
+            .map(|_| network.add_node(KarzanovNode::new()))
+            .collect();
+        for into in &layer {
+            for (i, from) in last_layer.iter().enumerate() {
+                // about half of the possible arcs, but never none
+                let wanted = capacity();
+                if i == 0 || wanted % 2 == 0 {
+                    network.connect(*from, *into, KarzanovArc::new(capacity()));
+                }
+            }
+        }
+        last_layer = layer;
+    }
+    let sink_id = network.add_node(KarzanovNode::new());
+    for from in last_layer {
+        network.connect(from, sink_id, KarzanovArc::new(capacity()));
+    }
+    (source_id, sink_id, network)
+}
+
+/// a random network of `nodes` nodes and `arcs` arcs between random distinct nodes, with capacities
+/// from 1 to `max_cap`. the source is the first node and the sink the last one, while the arcs may
+/// form cycles, run antiparallel or leave the sink out of reach. the same seed always gives the same network
+pub fn random_network(
+    nodes: usize,
+    arcs: usize,
+    max_cap: u32,
+    seed: u64,
+) -> (NodeId, NodeId, GraphNetwork<KarzanovNode, KarzanovArc>) {
+    let nodes = nodes.max(2);
+    let mut rng = XorShift::new(seed);
+    let mut network = GraphNetwork::new();
+    network.add_nodes(vec![KarzanovNode::new(); nodes].into_iter());
+    for _ in 0..arcs {
+        let from = (rng.next() % nodes as u64) as NodeId;
+        // a shift of 1 to `nodes - 1` never lands on `from` again
+        let into = (from + 1 + (rng.next() % (nodes as u64 - 1)) as NodeId) % nodes;
+        let capacity = (rng.next() % max_cap.max(1) as u64) as u32 + 1;
+        network.connect(from, into, KarzanovArc::new(capacity));
+    }
+    (0, nodes - 1, network)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::karzanov::maxflow;
+
+    #[test]
+    fn random_layered() {
+        for seed in 0..50 {
+            let (source, sink, mut network) = super::random_layered(4, 3, 10, seed);
+            assert_eq!(network.node_data.len(), 14);
+            maxflow(source, sink, &mut network).unwrap();
+            assert!(network.all_nodes_balanced(source, sink), "seed {}", seed);
+            assert!(network
+                .arc_data
+                .iter()
+                .flatten()
+                .all(|arc| arc.flow <= arc.capacity));
+        }
+    }
+
+    #[test]
+    fn random_network() {
+        for seed in 0..50 {
+            let (source, sink, network) = super::random_network(6, 12, 10, seed);
+            assert_eq!((source, sink), (0, 5));
+            assert_eq!(network.arc_count(), 12);
+            assert!((0..12).all(|arc_id| {
+                let (from, into) = network.arc_endpoints(arc_id).unwrap();
+                from != into
+            }));
+            let again = super::random_network(6, 12, 10, seed).2;
+            assert!((0..12).all(|arc_id| {
+                network.arc_endpoints(arc_id) == again.arc_endpoints(arc_id)
+                    && network.data_of_arc(arc_id).unwrap().capacity()
+                        == again.data_of_arc(arc_id).unwrap().capacity()
+            }));
+        }
+    }
+}
@@ -1,5 +1,4 @@
-use genawaiter::sync::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 pub type NodeId = usize;
 pub type ArcId = usize;
@@ -10,13 +9,67 @@ struct ArcConnection {
     into: NodeId,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct GraphNetwork<N, A> {
     pub node_data: Vec<Option<N>>, // Option is to support removal of nodes
     arcs_into: Vec<Vec<ArcId>>,    // The length of this vector is the number of nodes
     arcs_from: Vec<Vec<ArcId>>,    // The length of this vector is the number of nodes
     pub arc_data: Vec<Option<A>>,  // Option is to support removal of arcs
     arc_connections: Vec<ArcConnection>, // The length of this vector is the number of arcs
+    arcs_between: HashMap<(NodeId, NodeId), Vec<ArcId>>, // the live arcs of each pair of nodes
+    free_nodes: Vec<NodeId>,       // removed ids, reused by `add_node`
+    free_arcs: Vec<ArcId>,         // removed ids, reused by `connect`
+}
+
+impl<N, A> Default for GraphNetwork<N, A> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// two networks are equal if they have the same live nodes and arcs under the same ids,
+/// with equal data and endpoints. the holes of removed ones are ignored, but the ids are not,
+/// so a network with holes in the middle differs from its `clean`ed self
+impl<N: PartialEq, A: PartialEq> PartialEq for GraphNetwork<N, A> {
+    fn eq(&self, other: &Self) -> bool {
+        fn live_nodes<N, A>(network: &GraphNetwork<N, A>) -> Vec<(NodeId, &N)> {
+            (0..network.node_data.len())
+                .filter_map(|node_id| Some((node_id, network.node_data[node_id].as_ref()?)))
+                .collect()
+        }
+        fn live_arcs<N, A>(network: &GraphNetwork<N, A>) -> Vec<(ArcId, (NodeId, NodeId), &A)> {
+            (0..network.arc_data.len())
+                .filter_map(|arc_id| {
+                    let endpoints = network.arc_endpoints(arc_id)?;
+                    Some((arc_id, endpoints, network.arc_data[arc_id].as_ref()?))
+                })
+                .collect()
+        }
+        live_nodes(self) == live_nodes(other) && live_arcs(self) == live_arcs(other)
+    }
+}
+
+impl<N: Eq, A: Eq> Eq for GraphNetwork<N, A> {}
+
+/// the data of a node, panicking if it does not exist. `data_of_node` is the fallible way
+impl<N, A> std::ops::Index<NodeId> for GraphNetwork<N, A> {
+    type Output = N;
+
+    fn index(&self, node: NodeId) -> &N {
+        match self.node_data.get(node) {
+            Some(Some(data)) => data,
+            _ => panic!("node {} does not exist", node),
+        }
+    }
+}
+
+impl<N, A> std::ops::IndexMut<NodeId> for GraphNetwork<N, A> {
+    fn index_mut(&mut self, node: NodeId) -> &mut N {
+        match self.node_data.get_mut(node) {
+            Some(Some(data)) => data,
+            _ => panic!("node {} does not exist", node),
+        }
+    }
 }
 
 impl<'g, N, A> GraphNetwork<N, A> {
@@ -27,28 +80,59 @@ impl<'g, N, A> GraphNetwork<N, A> {
             arcs_from: Vec::new(),
             arc_data: Vec::new(),
             arc_connections: Vec::new(),
+            arcs_between: HashMap::new(),
+            free_nodes: Vec::new(),
+            free_arcs: Vec::new(),
         }
     }
 
-    pub fn clean(self) -> Self {
-        let mut old_new_map = HashMap::<NodeId, NodeId>::new();
-        let mut brand_new = Self::new();
+    pub fn clean(mut self) -> Self {
+        self.compact();
+        self
+    }
 
-        for (old_node_id, node_data) in self.node_data.into_iter().enumerate() {
+    /// remove the holes left by removed nodes and arcs in place, keeping the order of the rest,
+    /// and return the maps from the old ids to the new ones, e.g. to find a stored source again
+    pub fn compact(&mut self) -> (HashMap<NodeId, NodeId>, HashMap<ArcId, ArcId>) {
+        let old = std::mem::take(self);
+        let mut old_new_node_map = HashMap::<NodeId, NodeId>::new();
+        let mut old_new_arc_map = HashMap::<ArcId, ArcId>::new();
+
+        for (old_node_id, node_data) in old.node_data.into_iter().enumerate() {
             if let Some(node_data) = node_data {
-                let new_node_id = brand_new.add_node(node_data);
-                old_new_map.insert(old_node_id, new_node_id);
+                let new_node_id = self.add_node(node_data);
+                old_new_node_map.insert(old_node_id, new_node_id);
             }
         }
 
-        for (old_arc_id, arc_data) in self.arc_data.into_iter().enumerate() {
+        for (old_arc_id, arc_data) in old.arc_data.into_iter().enumerate() {
             if let Some(arc_data) = arc_data {
-                let ArcConnection { from, into } = self.arc_connections[old_arc_id];
-                brand_new.connect(old_new_map[&from], old_new_map[&into], arc_data);
+                let ArcConnection { from, into } = old.arc_connections[old_arc_id];
+                let new_arc_id =
+                    self.connect(old_new_node_map[&from], old_new_node_map[&into], arc_data);
+                old_new_arc_map.insert(old_arc_id, new_arc_id);
             }
         }
 
-        return brand_new;
+        (old_new_node_map, old_new_arc_map)
+    }
+
+    /// move the live nodes and arcs of `other` into this network, and return the map from
+    /// the node ids of `other` to their new ids, e.g. to assemble a network from components
+    pub fn merge(&mut self, other: GraphNetwork<N, A>) -> HashMap<NodeId, NodeId> {
+        let mut old_new_map = HashMap::<NodeId, NodeId>::new();
+        for (old_node_id, node_data) in other.node_data.into_iter().enumerate() {
+            if let Some(node_data) = node_data {
+                old_new_map.insert(old_node_id, self.add_node(node_data));
+            }
+        }
+        for (old_arc_id, arc_data) in other.arc_data.into_iter().enumerate() {
+            if let Some(arc_data) = arc_data {
+                let ArcConnection { from, into } = other.arc_connections[old_arc_id];
+                self.connect(old_new_map[&from], old_new_map[&into], arc_data);
+            }
+        }
+        old_new_map
     }
 
     pub fn is_node_in(&self, node: NodeId) -> bool {
@@ -60,14 +144,33 @@ impl<'g, N, A> GraphNetwork<N, A> {
         if !self.is_node_in(from) || !self.is_node_in(into) {
             return false;
         }
-        // if the same arc is in both the outarcs and inarcs, then it is an arc which connects the two nodes
-        for arc in &self.arcs_from[from] {
-            // skip 'None' arcs
-            if self.arc_data[arc.clone()].is_some() && self.arcs_into[into].contains(&arc) {
-                return true;
-            }
+        self.arcs_between.contains_key(&(from, into))
+    }
+
+    /// the number of live nodes, leaving out the holes of removed ones
+    pub fn node_count(&self) -> usize {
+        self.node_data.iter().flatten().count()
+    }
+
+    /// the number of live arcs, leaving out the holes of removed ones
+    pub fn arc_count(&self) -> usize {
+        self.arc_data.iter().flatten().count()
+    }
+
+    /// the number of live arcs entering a node, 0 for a removed node
+    pub fn in_degree(&self, node: NodeId) -> usize {
+        if !self.is_node_in(node) {
+            return 0;
         }
-        return false;
+        self.into_node(node).count()
+    }
+
+    /// the number of live arcs leaving a node, 0 for a removed node
+    pub fn out_degree(&self, node: NodeId) -> usize {
+        if !self.is_node_in(node) {
+            return 0;
+        }
+        self.from_node(node).count()
     }
 
     pub fn data_of_node(&self, node: NodeId) -> Option<&N> {
@@ -86,64 +189,213 @@ impl<'g, N, A> GraphNetwork<N, A> {
         self.arc_data[arc].as_mut()
     }
 
-    pub fn between_nodes(&'g self, from: NodeId, into: NodeId) -> impl Iterator<Item = ArcId> + 'g {
-        Gen::new(|co| async move {
-            // if the nodes do not exist, then the arc does not exist
-            if !self.is_node_in(from) || !self.is_node_in(into) {
-                panic!("Node does not exist");
-            }
-            // if the same arc is in both the outarcs and inarcs, then it is an arc which connects the two nodes
-            for arc_id in &self.arcs_from[from] {
-                if self.arc_data[arc_id.clone()].is_some() {
-                    if self.arcs_into[into].contains(arc_id) {
-                        co.yield_(arc_id.clone()).await;
-                    }
+    /// `(from, into)` of a live arc, e.g. of the ids `from_node` or `decompose_flow` give.
+    /// `None` if the arc is removed or out of range
+    pub fn arc_endpoints(&self, arc: ArcId) -> Option<(NodeId, NodeId)> {
+        // removed arcs keep their connection, but they do not connect anything anymore
+        self.arc_data.get(arc)?.as_ref()?;
+        let ArcConnection { from, into } = self.arc_connections[arc];
+        if !self.is_node_in(from) || !self.is_node_in(into) {
+            return None;
+        }
+        Some((from, into))
+    }
+
+    /// swap the data of a live arc, keeping its id and endpoints.
+    /// returns the old data, or `None` (and changes nothing) if the arc is removed or out of range
+    pub fn replace_arc_data(&mut self, arc: ArcId, data: A) -> Option<A> {
+        match self.arc_data.get_mut(arc) {
+            Some(Some(old)) => Some(std::mem::replace(old, data)),
+            _ => None,
+        }
+    }
+
+    pub fn between_nodes(&'g self, from: NodeId, into: NodeId) -> BetweenNodesIter<'g> {
+        // if the nodes do not exist, then the arc does not exist
+        let arcs = (self.is_node_in(from) && self.is_node_in(into)).then(|| {
+            self.arcs_between
+                .get(&(from, into))
+                .map_or([].iter(), |arcs| arcs.iter())
+        });
+        BetweenNodesIter { arcs }
+    }
+
+    pub fn from_node(&'g self, from: NodeId) -> FromNodeIter<'g, N, A> {
+        // if the node does not exist, then it has no arcs
+        let arcs = if self.is_node_in(from) {
+            self.arcs_from[from].iter()
+        } else {
+            [].iter()
+        };
+        FromNodeIter {
+            network: self,
+            arcs,
+        }
+    }
+
+    pub fn into_node(&'g self, into: NodeId) -> IntoNodeIter<'g, N, A> {
+        // if the node does not exist, then it has no arcs
+        let arcs = if self.is_node_in(into) {
+            self.arcs_into[into].iter()
+        } else {
+            [].iter()
+        };
+        IntoNodeIter {
+            network: self,
+            arcs,
+        }
+    }
+
+    /// the distinct heads of the live arcs leaving a node, in the order of their first arcs.
+    /// parallel arcs give their head once, and a removed node has none
+    pub fn successors(&self, node: NodeId) -> impl Iterator<Item = NodeId> {
+        let mut seen = HashSet::new();
+        let successors: Vec<NodeId> = if self.is_node_in(node) {
+            self.from_node(node)
+                .map(|(into, _)| into)
+                .filter(|into| self.is_node_in(*into) && seen.insert(*into))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        successors.into_iter()
+    }
+
+    /// the distinct tails of the live arcs entering a node, in the order of their first arcs.
+    /// parallel arcs give their tail once, and a removed node has none
+    pub fn predecessors(&self, node: NodeId) -> impl Iterator<Item = NodeId> {
+        let mut seen = HashSet::new();
+        let predecessors: Vec<NodeId> = if self.is_node_in(node) {
+            self.into_node(node)
+                .map(|(from, _)| from)
+                .filter(|from| self.is_node_in(*from) && seen.insert(*from))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        predecessors.into_iter()
+    }
+
+    /// reachability of every node id from `start`, along the arcs (`forward`) or against them
+    fn reachable_from(&self, start: NodeId, forward: bool) -> Vec<bool> {
+        let mut reached = vec![false; self.node_data.len()];
+        if !self.is_node_in(start) {
+            return reached;
+        }
+        reached[start] = true;
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            let neighbors: Vec<(NodeId, ArcId)> = if forward {
+                self.from_node(node).collect()
+            } else {
+                self.into_node(node).collect()
+            };
+            for (next, _) in neighbors {
+                // skip the dangling arcs of removed nodes
+                if self.is_node_in(next) && !reached[next] {
+                    reached[next] = true;
+                    stack.push(next);
                 }
             }
-        })
-        .into_iter()
+        }
+        reached
     }
 
-    pub fn from_node(&'g self, from: NodeId) -> impl Iterator<Item = (NodeId, ArcId)> + 'g {
-        Gen::new(|co| async move {
-            // if the nodes do not exist, then the arc does not exist
-            if !self.is_node_in(from) {
-                panic!("Node does not exist");
-            }
-            // if the same arc is in both the outarcs and inarcs, then it is an arc which connects the two nodes
-            for arc_id in &self.arcs_from[from] {
-                if self.arc_data[arc_id.clone()].is_some() {
-                    co.yield_((self.arc_connections[arc_id.clone()].into, arc_id.clone()))
-                        .await;
+    /// whether each node id lies on some path from `source` to `sink`.
+    /// removed nodes are `false`
+    pub fn st_participation_mask(&self, source: NodeId, sink: NodeId) -> Vec<bool> {
+        let from_source = self.reachable_from(source, true);
+        let into_sink = self.reachable_from(sink, false);
+        from_source
+            .into_iter()
+            .zip(into_sink)
+            .map(|(from_source, into_sink)| from_source && into_sink)
+            .collect()
+    }
+
+    /// a node on a directed cycle among the nodes on paths from `source` to `sink`, if there is one.
+    /// a depth-first search that meets a node still on its stack has closed a cycle
+    pub fn find_cycle_between(&self, source: NodeId, sink: NodeId) -> Option<NodeId> {
+        let on_path = self.st_participation_mask(source, sink);
+        if !on_path.get(source).copied().unwrap_or(false) {
+            return None;
+        }
+        let mut visited = vec![false; self.node_data.len()];
+        let mut on_stack = vec![false; self.node_data.len()];
+        visited[source] = true;
+        on_stack[source] = true;
+        let mut stack = vec![(source, self.from_node(source))];
+        while let Some((node, arcs)) = stack.last_mut() {
+            match arcs.next() {
+                Some((next, _)) if on_path[next] => {
+                    if on_stack[next] {
+                        return Some(next);
+                    }
+                    if !visited[next] {
+                        visited[next] = true;
+                        on_stack[next] = true;
+                        stack.push((next, self.from_node(next)));
+                    }
+                }
+                Some(_) => {}
+                None => {
+                    on_stack[*node] = false;
+                    stack.pop();
                 }
             }
-        })
-        .into_iter()
+        }
+        None
     }
 
-    pub fn into_node(&'g self, into: NodeId) -> impl Iterator<Item = (NodeId, ArcId)> + 'g {
-        Gen::new(|co| async move {
-            // if the nodes do not exist, then the arc does not exist
-            if !self.is_node_in(into) {
-                panic!("Node does not exist");
+    /// the only node without incoming arcs and the only node without outgoing arcs, if both are unique
+    pub fn guess_source_sink(&self) -> Option<(NodeId, NodeId)> {
+        let mut sources = Vec::new();
+        let mut sinks = Vec::new();
+        for node_id in (0..self.node_data.len()).filter(|node_id| self.is_node_in(*node_id)) {
+            if self.into_node(node_id).next().is_none() {
+                sources.push(node_id);
             }
-            // if the same arc is in both the outarcs and inarcs, then it is an arc which connects the two nodes
-            for arc_id in &self.arcs_into[into] {
-                if self.arc_data[arc_id.clone()].is_some() {
-                    co.yield_((self.arc_connections[arc_id.clone()].from, arc_id.clone()))
-                        .await;
-                }
+            if self.from_node(node_id).next().is_none() {
+                sinks.push(node_id);
             }
-        })
-        .into_iter()
+        }
+        match (sources.as_slice(), sinks.as_slice()) {
+            ([source_id], [sink_id]) => Some((*source_id, *sink_id)),
+            _ => None,
+        }
+    }
+
+    /// node-by-arc incidence matrix of the live nodes and arcs, in the order of their ids.
+    /// an entry is +1 where the arc leaves the node and -1 where it enters
+    pub fn incidence_matrix(&self) -> Vec<Vec<i32>> {
+        let rows: HashMap<NodeId, usize> = (0..self.node_data.len())
+            .filter(|node| self.is_node_in(*node))
+            .enumerate()
+            .map(|(row, node)| (node, row))
+            .collect();
+        let arcs: Vec<(NodeId, NodeId)> = (0..self.arc_data.len())
+            .filter_map(|arc| self.arc_endpoints(arc))
+            .collect();
+
+        let mut matrix = vec![vec![0; arcs.len()]; rows.len()];
+        for (column, (from, into)) in arcs.into_iter().enumerate() {
+            matrix[rows[&from]][column] += 1;
+            matrix[rows[&into]][column] -= 1;
+        }
+        matrix
     }
 
     pub fn add_node(&mut self, data: N) -> NodeId {
+        // reuse a removed id, whose adjacency lists were emptied on removal
+        if let Some(node_id) = self.free_nodes.pop() {
+            self.node_data[node_id] = Some(data);
+            return node_id;
+        }
         let node_id = self.node_data.len();
         self.node_data.push(Some(data));
         self.arcs_into.push(Vec::new());
         self.arcs_from.push(Vec::new());
-        return node_id;
+        node_id
     }
 
     pub fn add_nodes<I: Iterator<Item = N>>(&mut self, data: I) {
@@ -157,9 +409,21 @@ impl<'g, N, A> GraphNetwork<N, A> {
         if !self.is_node_in(node) {
             return None;
         }
-        // release the arcs
-        self.arcs_into[node].clear();
-        self.arcs_from[node].clear();
+        // remove the incident arcs, also from the lists of the other endpoints
+        let arcs: Vec<ArcId> = self.arcs_from[node]
+            .drain(..)
+            .chain(self.arcs_into[node].drain(..))
+            .collect();
+        for arc_id in arcs {
+            let ArcConnection { from, into } = self.arc_connections[arc_id];
+            self.arcs_between.remove(&(from, into));
+            self.arcs_from[from].retain(|other| *other != arc_id);
+            self.arcs_into[into].retain(|other| *other != arc_id);
+            if self.arc_data[arc_id].take().is_some() {
+                self.free_arcs.push(arc_id);
+            }
+        }
+        self.free_nodes.push(node);
         self.node_data[node].take()
     }
 
@@ -167,12 +431,26 @@ impl<'g, N, A> GraphNetwork<N, A> {
         if !self.is_node_in(from) || !self.is_node_in(into) {
             panic!("Node does not exist");
         }
-        let arc_id = self.arc_data.len();
-        self.arc_data.push(Some(value));
-        self.arc_connections.push(ArcConnection { from, into });
+        let arc_id = match self.free_arcs.pop() {
+            // reuse a removed id
+            Some(arc_id) => {
+                self.arc_data[arc_id] = Some(value);
+                self.arc_connections[arc_id] = ArcConnection { from, into };
+                arc_id
+            }
+            None => {
+                self.arc_data.push(Some(value));
+                self.arc_connections.push(ArcConnection { from, into });
+                self.arc_data.len() - 1
+            }
+        };
         self.arcs_from[from].push(arc_id);
         self.arcs_into[into].push(arc_id);
-        return arc_id;
+        self.arcs_between
+            .entry((from, into))
+            .or_default()
+            .push(arc_id);
+        arc_id
     }
 
     pub fn bulk_connect<I: Iterator<Item = (NodeId, NodeId, A)>>(&mut self, arcs: I) {
@@ -187,14 +465,207 @@ impl<'g, N, A> GraphNetwork<N, A> {
         if self.arc_data.len() <= arc {
             return None;
         }
+        self.arc_data[arc].as_ref()?;
+        // unlink the arc from the adjacency lists and the index
+        let ArcConnection { from, into } = self.arc_connections[arc];
+        self.arcs_from[from].retain(|arc_id| *arc_id != arc);
+        self.arcs_into[into].retain(|arc_id| *arc_id != arc);
+        if let Some(arcs) = self.arcs_between.get_mut(&(from, into)) {
+            arcs.retain(|arc_id| *arc_id != arc);
+            if arcs.is_empty() {
+                self.arcs_between.remove(&(from, into));
+            }
+        }
+        self.free_arcs.push(arc);
         self.arc_data[arc].take()
-        // arc_connections is left as it.
+        // arc_connections is left as it, until the id is reused.
+    }
+}
+
+impl<N: Clone, A: Clone> GraphNetwork<N, A> {
+    /// a copy with every arc flipped to run `into -> from`, keeping the data.
+    /// the holes of removed nodes and arcs are dropped, so the ids are compacted as by `clean`
+    pub fn reverse(&self) -> GraphNetwork<N, A> {
+        let mut reversed = GraphNetwork::new();
+        let mut old_new_map = HashMap::<NodeId, NodeId>::new();
+        for (old_node_id, node_data) in self.node_data.iter().enumerate() {
+            if let Some(node_data) = node_data {
+                old_new_map.insert(old_node_id, reversed.add_node(node_data.clone()));
+            }
+        }
+        for arc_id in 0..self.arc_data.len() {
+            if let Some((from, into)) = self.arc_endpoints(arc_id) {
+                let arc_data = self.arc_data[arc_id].clone().unwrap();
+                reversed.connect(old_new_map[&into], old_new_map[&from], arc_data);
+            }
+        }
+        reversed
+    }
+
+    /// a copy of the given nodes and the arcs between them, with the map from their ids to the new ones.
+    /// the new ids follow the order of `nodes`, where removed nodes and repeats are skipped
+    pub fn subgraph(&self, nodes: &[NodeId]) -> (GraphNetwork<N, A>, HashMap<NodeId, NodeId>) {
+        let mut subgraph = GraphNetwork::new();
+        let mut old_new_map = HashMap::<NodeId, NodeId>::new();
+        for node_id in nodes {
+            if !self.is_node_in(*node_id) || old_new_map.contains_key(node_id) {
+                continue;
+            }
+            let node_data = self.node_data[*node_id].clone().unwrap();
+            old_new_map.insert(*node_id, subgraph.add_node(node_data));
+        }
+        for arc_id in 0..self.arc_data.len() {
+            let Some((from, into)) = self.arc_endpoints(arc_id) else {
+                continue;
+            };
+            if let (Some(from), Some(into)) = (old_new_map.get(&from), old_new_map.get(&into)) {
+                let arc_data = self.arc_data[arc_id].clone().unwrap();
+                subgraph.connect(*from, *into, arc_data);
+            }
+        }
+        (subgraph, old_new_map)
+    }
+}
+
+/// the arcs from one node into another, as `GraphNetwork::between_nodes` gives them.
+/// panics on the first `next` if either node does not exist
+pub struct BetweenNodesIter<'g> {
+    arcs: Option<std::slice::Iter<'g, ArcId>>, // `None` if a node does not exist
+}
+
+impl Iterator for BetweenNodesIter<'_> {
+    type Item = ArcId;
+
+    fn next(&mut self) -> Option<ArcId> {
+        let arcs = self.arcs.as_mut().expect("Node does not exist");
+        arcs.next().copied()
+    }
+}
+
+/// `(into, arc)` of the arcs leaving a node, as `GraphNetwork::from_node` gives them.
+/// empty if the node does not exist
+pub struct FromNodeIter<'g, N, A> {
+    network: &'g GraphNetwork<N, A>,
+    arcs: std::slice::Iter<'g, ArcId>,
+}
+
+impl<N, A> Iterator for FromNodeIter<'_, N, A> {
+    type Item = (NodeId, ArcId);
+
+    fn next(&mut self) -> Option<(NodeId, ArcId)> {
+        // skip 'None' arcs
+        self.arcs
+            .find(|arc_id| self.network.arc_data[**arc_id].is_some())
+            .map(|arc_id| (self.network.arc_connections[*arc_id].into, *arc_id))
+    }
+}
+
+/// `(from, arc)` of the arcs entering a node, as `GraphNetwork::into_node` gives them.
+/// empty if the node does not exist
+pub struct IntoNodeIter<'g, N, A> {
+    network: &'g GraphNetwork<N, A>,
+    arcs: std::slice::Iter<'g, ArcId>,
+}
+
+impl<N, A> Iterator for IntoNodeIter<'_, N, A> {
+    type Item = (NodeId, ArcId);
+
+    fn next(&mut self) -> Option<(NodeId, ArcId)> {
+        // skip 'None' arcs
+        self.arcs
+            .find(|arc_id| self.network.arc_data[**arc_id].is_some())
+            .map(|arc_id| (self.network.arc_connections[*arc_id].from, *arc_id))
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use std::collections::HashMap;
+
+    use super::{ArcConnection, GraphNetwork, NodeId};
+
+    /// the adjacency lists follow from the connections, so they are left out
+    #[derive(Serialize, Deserialize)]
+    struct Stored<Nodes, Arcs> {
+        node_data: Nodes,
+        arc_data: Arcs,
+        arc_connections: Vec<(NodeId, NodeId)>,
+    }
+
+    impl<N: Serialize, A: Serialize> Serialize for GraphNetwork<N, A> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            Stored {
+                node_data: &self.node_data,
+                arc_data: &self.arc_data,
+                arc_connections: self
+                    .arc_connections
+                    .iter()
+                    .map(|connection| (connection.from, connection.into))
+                    .collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, N: Deserialize<'de>, A: Deserialize<'de>> Deserialize<'de> for GraphNetwork<N, A> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let stored = Stored::<Vec<Option<N>>, Vec<Option<A>>>::deserialize(deserializer)?;
+            if stored.arc_data.len() != stored.arc_connections.len() {
+                return Err(D::Error::custom("one connection per arc is expected"));
+            }
+            let node_count = stored.node_data.len();
+            let mut network = GraphNetwork {
+                node_data: stored.node_data,
+                arcs_into: vec![Vec::new(); node_count],
+                arcs_from: vec![Vec::new(); node_count],
+                arc_data: stored.arc_data,
+                arc_connections: Vec::with_capacity(stored.arc_connections.len()),
+                arcs_between: HashMap::new(),
+                free_nodes: Vec::new(),
+                free_arcs: Vec::new(),
+            };
+            for (arc_id, (from, into)) in stored.arc_connections.into_iter().enumerate() {
+                if from >= node_count || into >= node_count {
+                    return Err(D::Error::custom(format!(
+                        "arc {} connects a node out of range",
+                        arc_id
+                    )));
+                }
+                // a removed arc is in no list, as after `disconnect`,
+                // and neither is an arc of a removed node, as after `remove_node`
+                if network.node_data[from].is_none() || network.node_data[into].is_none() {
+                    network.arc_data[arc_id] = None;
+                }
+                if network.arc_data[arc_id].is_some() {
+                    network.arcs_from[from].push(arc_id);
+                    network.arcs_into[into].push(arc_id);
+                    network
+                        .arcs_between
+                        .entry((from, into))
+                        .or_default()
+                        .push(arc_id);
+                }
+                network.arc_connections.push(ArcConnection { from, into });
+            }
+            network.free_nodes = (0..node_count)
+                .filter(|node_id| network.node_data[*node_id].is_none())
+                .collect();
+            network.free_arcs = (0..network.arc_data.len())
+                .filter(|arc_id| network.arc_data[*arc_id].is_none())
+                .collect();
+            Ok(network)
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::instances::*;
+    use crate::karzanov::{KarzanovArc, KarzanovNode};
 
     #[test]
     fn test_network() -> Result<(), ()> {
@@ -222,11 +693,427 @@ mod tests {
             network.into_node(3).collect::<Vec<_>>(),
             vec![(1, 2), (2, 3)]
         );
-        assert_eq!(network.is_arc_in(1, 4), false);
-        assert_eq!(network.is_node_in(1), true);
-        assert_eq!(network.is_node_in(6), false);
+        assert!(!network.is_arc_in(1, 4));
+        assert!(network.is_node_in(1));
+        assert!(!network.is_node_in(6));
         assert_eq!(network.between_nodes(0, 1).collect::<Vec<_>>(), vec![0]);
         assert_eq!(network.data_of_node(0), Some(&0));
         Ok(())
     }
+
+    #[test]
+    fn test_replace_arc_data() {
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(vec![0, 1, 2].into_iter());
+        network.bulk_connect(vec![(0, 1, 10), (1, 2, 20)].into_iter());
+
+        assert_eq!(network.replace_arc_data(1, 25), Some(20));
+        assert_eq!(network.data_of_arc(1), Some(&25));
+        assert_eq!(network.between_nodes(1, 2).collect::<Vec<_>>(), vec![1]);
+        assert_eq!(network.from_node(1).collect::<Vec<_>>(), vec![(2, 1)]);
+        assert_eq!(network.into_node(2).collect::<Vec<_>>(), vec![(1, 1)]);
+
+        network.disconnect(0);
+        assert_eq!(network.replace_arc_data(0, 15), None);
+        assert_eq!(network.data_of_arc(0), None);
+        assert_eq!(network.replace_arc_data(5, 15), None);
+    }
+
+    #[test]
+    fn test_incidence_matrix() {
+        let (_, _, network) = network_instance3();
+        assert_eq!(
+            network.incidence_matrix(),
+            vec![vec![1, 0], vec![-1, 1], vec![0, -1]]
+        );
+
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(vec![0, 1, 2, 3].into_iter());
+        network.bulk_connect(vec![(0, 1, 0), (1, 3, 0), (0, 3, 0), (2, 3, 0)].into_iter());
+        network.remove_node(2);
+        network.disconnect(0);
+        // a removed node has no row, and neither a removed arc nor its dangling arc has a column
+        assert_eq!(
+            network.incidence_matrix(),
+            vec![vec![0, 1], vec![1, 0], vec![-1, -1]]
+        );
+    }
+
+    #[test]
+    fn test_st_participation_mask() {
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(vec![0, 1, 2, 3, 4, 5].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, 0),
+                (1, 3, 0),
+                (0, 2, 0), // 2 is a dead end
+                (4, 3, 0), // 4 cannot be reached from the source
+                (5, 3, 0),
+            ]
+            .into_iter(),
+        );
+        network.remove_node(5);
+        assert_eq!(
+            network.st_participation_mask(0, 3),
+            vec![true, true, false, true, false, false]
+        );
+    }
+
+    #[test]
+    fn guess_source_sink() {
+        let (_, _, mut network) = network_instance1();
+        assert_eq!(network.guess_source_sink(), Some((0, 5)));
+        // 4 becomes a second sink
+        network.disconnect(6);
+        assert_eq!(network.guess_source_sink(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let (_, _, mut network) = network_instance1();
+        network.disconnect(2);
+        let json = serde_json::to_string(&network).unwrap();
+        let restored: GraphNetwork<KarzanovNode, KarzanovArc> =
+            serde_json::from_str(&json).unwrap();
+        for node_id in 0..network.node_data.len() {
+            assert_eq!(
+                restored.from_node(node_id).collect::<Vec<_>>(),
+                network.from_node(node_id).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                restored.into_node(node_id).collect::<Vec<_>>(),
+                network.into_node(node_id).collect::<Vec<_>>()
+            );
+        }
+        assert!(restored.data_of_arc(2).is_none());
+
+        let broken = json.replace("[1,3]", "[1,9]");
+        assert!(serde_json::from_str::<GraphNetwork<KarzanovNode, KarzanovArc>>(&broken).is_err());
+    }
+
+    #[test]
+    fn adjacency_iterators() {
+        let (_, _, mut network) = network_instance1();
+        network.connect(2, 3, KarzanovArc::new(1));
+        network.disconnect(3);
+        assert_eq!(
+            network.from_node(2).collect::<Vec<_>>(),
+            vec![(4, 4), (3, 7)]
+        );
+        assert_eq!(
+            network.into_node(3).collect::<Vec<_>>(),
+            vec![(1, 2), (2, 7)]
+        );
+        assert_eq!(network.between_nodes(2, 3).collect::<Vec<_>>(), vec![7]);
+        assert_eq!(network.between_nodes(3, 2).count(), 0);
+
+        network.remove_node(4);
+        assert_eq!(network.into_node(5).collect::<Vec<_>>(), vec![(3, 5)]);
+        // a missing node has no arcs either way
+        assert_eq!(network.from_node(4).count(), 0);
+        assert_eq!(network.into_node(4).count(), 0);
+        assert_eq!(network.from_node(99).count(), 0);
+    }
+
+    #[test]
+    fn arcs_between_index() {
+        // every ordered pair of 5 nodes, twice
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(0..5);
+        for from in 0..5 {
+            for into in 0..5 {
+                network.connect(from, into, 0);
+                network.connect(from, into, 1);
+            }
+        }
+        for arc_id in (0..network.arc_data.len()).step_by(3) {
+            network.disconnect(arc_id);
+        }
+        network.remove_node(2);
+
+        for from in 0..5 {
+            for into in 0..5 {
+                let expected: Vec<ArcId> = (0..network.arc_data.len())
+                    .filter(|arc_id| network.arc_endpoints(*arc_id) == Some((from, into)))
+                    .collect();
+                assert_eq!(network.is_arc_in(from, into), !expected.is_empty());
+                if network.is_node_in(from) && network.is_node_in(into) {
+                    assert_eq!(
+                        network.between_nodes(from, into).collect::<Vec<_>>(),
+                        expected
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn remove_node_purges_its_arcs() {
+        let (_, _, mut network) = network_instance1();
+        network.connect(2, 2, KarzanovArc::new(1));
+        assert!(network.remove_node(2).is_some());
+        for node_id in [0, 1, 3, 4, 5] {
+            assert!(network.from_node(node_id).all(|(into, _)| into != 2));
+            assert!(network.into_node(node_id).all(|(from, _)| from != 2));
+        }
+        // the arcs 0 -> 2, 2 -> 3, 2 -> 4 and the loop at 2 are gone
+        for arc_id in [1, 3, 4, 7] {
+            assert!(network.data_of_arc(arc_id).is_none());
+        }
+        assert_eq!(network.from_node(0).collect::<Vec<_>>(), vec![(1, 0)]);
+        assert_eq!(network.into_node(3).collect::<Vec<_>>(), vec![(1, 2)]);
+        assert!(network.remove_node(2).is_none());
+    }
+
+    #[test]
+    fn disconnect_unlinks() {
+        let (_, _, mut network) = network_instance1();
+        // 2 -> 3
+        assert!(network.disconnect(3).is_some());
+        assert!(!network.arcs_from[2].contains(&3));
+        assert!(!network.arcs_into[3].contains(&3));
+        assert!(network.is_arc_in(2, 4));
+        assert!(!network.is_arc_in(2, 3));
+        assert_eq!(network.from_node(2).collect::<Vec<_>>(), vec![(4, 4)]);
+        assert!(network.disconnect(3).is_none());
+
+        // churn leaves no stale ids behind
+        for _ in 0..100 {
+            let arc_id = network.connect(2, 3, KarzanovArc::new(1));
+            network.disconnect(arc_id);
+        }
+        assert_eq!(network.arcs_from[2], vec![4]);
+        assert_eq!(network.arcs_into[3], vec![2]);
+    }
+
+    #[test]
+    fn reuse_removed_ids() {
+        let (_, _, mut network) = network_instance1();
+        network.remove_node(2);
+        let node_id = network.add_node(KarzanovNode::new());
+        assert_eq!(node_id, 2);
+        assert_eq!(network.node_data.len(), 6);
+        assert_eq!(network.from_node(2).count(), 0);
+        assert_eq!(network.into_node(2).count(), 0);
+        assert!(!network.is_arc_in(0, 2));
+
+        // the arcs 0 -> 2, 2 -> 3 and 2 -> 4 left their ids behind
+        let arc_id = network.connect(0, 2, KarzanovArc::new(7));
+        assert!([1, 3, 4].contains(&arc_id));
+        assert_eq!(network.arc_data.len(), 7);
+        assert_eq!(network.arc_endpoints(arc_id), Some((0, 2)));
+        assert_eq!(network.data_of_arc(arc_id).unwrap().capacity, 7);
+        assert_eq!(network.into_node(2).collect::<Vec<_>>(), vec![(0, arc_id)]);
+
+        network.disconnect(0);
+        assert_eq!(network.connect(1, 2, KarzanovArc::new(1)), 0);
+        assert_eq!(network.arc_endpoints(0), Some((1, 2)));
+    }
+
+    #[test]
+    fn compact() {
+        let (source, sink, mut network) = network_instance1();
+        network.remove_node(2);
+        let (node_map, arc_map) = network.compact();
+        assert_eq!(network.node_data.len(), 5);
+        // 0 -> 1, 1 -> 3, 3 -> 5 and 4 -> 5 are left
+        assert_eq!(network.arc_data.len(), 4);
+        assert_eq!(node_map[&source], 0);
+        assert_eq!(node_map[&sink], 4);
+        assert!(!node_map.contains_key(&2));
+        assert_eq!(arc_map, HashMap::from([(0, 0), (2, 1), (5, 2), (6, 3)]));
+        assert_eq!(
+            network.arc_endpoints(arc_map[&5]),
+            Some((node_map[&3], node_map[&sink]))
+        );
+        assert!(network.free_nodes.is_empty() && network.free_arcs.is_empty());
+    }
+
+    #[test]
+    fn live_counts() {
+        let (_, _, mut network) = network_instance1();
+        assert_eq!((network.node_count(), network.arc_count()), (6, 7));
+        network.disconnect(6);
+        // the arcs 0 -> 2, 2 -> 3 and 2 -> 4 go with the node
+        network.remove_node(2);
+        assert_eq!(network.node_data.len(), 6);
+        assert_eq!((network.node_count(), network.arc_count()), (5, 3));
+    }
+
+    #[test]
+    fn degrees() {
+        let (_, _, mut network) = network_instance2();
+        assert_eq!((network.in_degree(0), network.out_degree(0)), (0, 2));
+        assert_eq!((network.in_degree(3), network.out_degree(3)), (1, 3));
+        assert_eq!((network.in_degree(5), network.out_degree(5)), (3, 1));
+        assert_eq!((network.in_degree(1), network.out_degree(1)), (2, 2));
+        // 3 -> 1
+        network.disconnect(5);
+        assert_eq!(network.in_degree(1), 1);
+        assert_eq!(network.out_degree(3), 2);
+
+        network.remove_node(3);
+        assert_eq!((network.in_degree(3), network.out_degree(3)), (0, 0));
+        assert_eq!((network.in_degree(99), network.out_degree(99)), (0, 0));
+    }
+
+    #[test]
+    fn successors_and_predecessors() {
+        let (_, _, mut network) = network_instance1();
+        network.connect(0, 2, KarzanovArc::new(1));
+        network.connect(2, 2, KarzanovArc::new(1));
+        assert_eq!(network.successors(0).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(network.successors(2).collect::<Vec<_>>(), vec![3, 4, 2]);
+        assert_eq!(network.predecessors(2).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(network.predecessors(5).collect::<Vec<_>>(), vec![3, 4]);
+
+        network.remove_node(1);
+        assert_eq!(network.successors(0).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(network.predecessors(3).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(network.successors(1).count(), 0);
+    }
+
+    #[test]
+    fn reverse() {
+        let (_, _, network) = network_instance2();
+        let reversed = network.reverse();
+        let twice = reversed.reverse();
+        for node_id in 0..network.node_data.len() {
+            assert_eq!(
+                reversed.from_node(node_id).collect::<Vec<_>>(),
+                network.into_node(node_id).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                twice.from_node(node_id).collect::<Vec<_>>(),
+                network.from_node(node_id).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(reversed.data_of_arc(5).unwrap().capacity, 4);
+
+        // 0 -> 2 -> 3 of 0 -> 1 -> 2 -> 3, with 1 gone
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(0..4);
+        network.bulk_connect(vec![(0, 1, 1), (1, 2, 2), (2, 3, 3), (0, 2, 4)].into_iter());
+        network.remove_node(1);
+        let reversed = network.reverse();
+        assert_eq!(reversed.node_data, vec![Some(0), Some(2), Some(3)]);
+        assert_eq!(reversed.from_node(2).collect::<Vec<_>>(), vec![(1, 0)]);
+        assert_eq!(reversed.from_node(1).collect::<Vec<_>>(), vec![(0, 1)]);
+        assert_eq!(reversed.arc_data, vec![Some(3), Some(4)]);
+    }
+
+    #[test]
+    fn subgraph() {
+        let (_, _, network) = network_instance1();
+        let (subgraph, node_map) = network.subgraph(&[0, 1, 2, 3]);
+        // 0 -> 1, 0 -> 2, 1 -> 3 and 2 -> 3, but not 2 -> 4 or 3 -> 5
+        assert_eq!(subgraph.node_count(), 4);
+        assert_eq!(subgraph.arc_count(), 4);
+        assert_eq!(node_map, HashMap::from([(0, 0), (1, 1), (2, 2), (3, 3)]));
+        assert!(subgraph.is_arc_in(2, 3));
+
+        // the order of the given nodes sets the new ids
+        let (subgraph, node_map) = network.subgraph(&[5, 3, 3, 9]);
+        assert_eq!(node_map, HashMap::from([(5, 0), (3, 1)]));
+        assert_eq!(subgraph.from_node(1).collect::<Vec<_>>(), vec![(0, 0)]);
+    }
+
+    #[test]
+    fn merge() {
+        let (source, sink, mut network) = network_instance4();
+        let (other_source, other_sink, other) = network_instance4();
+        let node_map = network.merge(other);
+        assert_eq!(node_map, HashMap::from([(0, 2), (1, 3)]));
+        assert_eq!(network.node_count(), 4);
+        assert_eq!(network.arc_count(), 2);
+        assert_eq!(network.between_nodes(source, sink).count(), 1);
+        assert_eq!(
+            network
+                .between_nodes(node_map[&other_source], node_map[&other_sink])
+                .count(),
+            1
+        );
+        assert!(!network.is_arc_in(source, node_map[&other_sink]));
+    }
+
+    #[test]
+    fn structural_equality() {
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(0..4);
+        network.bulk_connect(vec![(0, 1, 1), (1, 2, 2), (2, 3, 3), (0, 2, 4)].into_iter());
+        assert_eq!(network, network.clone());
+
+        let mut other = network.clone();
+        other.replace_arc_data(3, 5);
+        assert_ne!(network, other);
+
+        // with a hole in the middle, the ids shift on cleaning
+        network.remove_node(1);
+        let cleaned = network.clone().clean();
+        assert_ne!(network, cleaned);
+        network.compact();
+        assert_eq!(network, cleaned);
+
+        // holes at the end do not shift anything
+        let mut network = cleaned.clone();
+        let node_id = network.add_node(9);
+        network.connect(0, node_id, 9);
+        network.remove_node(node_id);
+        assert_eq!(network, cleaned);
+    }
+
+    #[test]
+    fn index_node_data() {
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(vec![10, 11, 12].into_iter());
+        assert_eq!(network[0], 10);
+        network[1] += 5;
+        assert_eq!(network.data_of_node(1), Some(&16));
+    }
+
+    #[test]
+    #[should_panic(expected = "node 1 does not exist")]
+    fn index_removed_node() {
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(vec![10, 11, 12].into_iter());
+        network.remove_node(1);
+        let _ = network[1];
+    }
+
+    #[test]
+    fn arc_endpoints() {
+        let (_, _, mut network) = network_instance1();
+        assert_eq!(network.arc_endpoints(4), Some((2, 4)));
+        network.disconnect(4);
+        assert_eq!(network.arc_endpoints(4), None);
+        assert_eq!(network.arc_endpoints(99), None);
+    }
+
+    #[test]
+    fn test_find_cycle_between() {
+        let (source, sink, network) = network_instance2();
+        assert_eq!(network.find_cycle_between(source, sink), None);
+
+        // 1 -> 2 -> 3 -> 1 on the way from 0 to 4, and 5 -> 6 -> 5 out of the way
+        let mut network = GraphNetwork::<usize, i32>::new();
+        network.add_nodes(0..7);
+        network.bulk_connect(
+            vec![
+                (0, 1, 0),
+                (1, 2, 0),
+                (2, 3, 0),
+                (3, 1, 0),
+                (3, 4, 0),
+                (5, 6, 0),
+                (6, 5, 0),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(network.find_cycle_between(0, 4), Some(1));
+        assert_eq!(network.find_cycle_between(5, 4), None);
+        network.disconnect(3);
+        assert_eq!(network.find_cycle_between(0, 4), None);
+    }
 }
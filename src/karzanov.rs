@@ -1,388 +1,3952 @@
 use std::collections::HashMap;
+use std::fmt::Debug;
+use std::ops::{Add, Sub};
+use std::time::{Duration, Instant};
 
 use crate::graph::{ArcId, GraphNetwork, NodeId};
-use crate::utils::min;
+use crate::utils::{min, XorShift};
+
+/// the type of capacities and flows
+pub trait Capacity:
+    Copy + Ord + Debug + Send + Sync + Add<Output = Self> + Sub<Output = Self>
+{
+    fn zero() -> Self;
+
+    /// the sum, or `None` if it does not fit
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// the difference, or `None` if it falls below zero
+    fn checked_sub(self, other: Self) -> Option<Self>;
+
+    /// the same amount as a `u64`, e.g. to sum up many of them
+    fn to_u64(self) -> u64;
+}
+
+macro_rules! impl_capacity {
+    ($($t:ty),*) => {
+        $(impl Capacity for $t {
+            fn zero() -> Self {
+                0
+            }
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+
+            fn to_u64(self) -> u64 {
+                self as u64
+            }
+        })*
+    };
+}
+
+impl_capacity!(u32, u64, usize);
 
 #[derive(Debug, Clone)]
-pub struct KarzanovNode {
-    stack: Vec<(ArcId, u32)>,
-    grouped: bool, // to group nodes by layers
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KarzanovNode<C: Capacity = u32> {
+    stack: Vec<(ArcId, C)>,
+    layer: Option<usize>, // the distance from the source within the level graph
+    blocked: bool,        // no more flux may come in
 }
 
-impl KarzanovNode {
-    pub fn new() -> Self {
+impl<C: Capacity> Default for KarzanovNode<C> {
+    fn default() -> Self {
         KarzanovNode {
             stack: Vec::new(),
-            grouped: false,
+            layer: None,
+            blocked: false,
         }
     }
 }
 
-#[derive(Debug)]
-pub struct KarzanovArc {
-    capacity: u32,
-    flow: u32,
-    open: bool,
+impl KarzanovNode {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KarzanovArc<C: Capacity = u32> {
+    pub(crate) capacity: C,
+    pub(crate) flow: C,
+    pub(crate) open: bool,
+    // a locked arc keeps its flow through the solves
+    pub(crate) locked: bool,
+    // the least flow `feasible_flow` lets the arc carry
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) lower: C,
+    // the opposite arc of an undirected edge, whose flow offsets this one
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub(crate) twin: Option<ArcId>,
+}
+
+/// a zero-capacity arc, closed until a solve opens it. it never carries flow
+impl<C: Capacity> Default for KarzanovArc<C> {
+    fn default() -> Self {
+        KarzanovArc {
+            open: false,
+            ..Self::from_capacity(C::zero())
+        }
+    }
 }
 
 impl KarzanovArc {
     pub fn new(capacity: u32) -> Self {
+        Self::from_capacity(capacity)
+    }
+
+    /// an arc which must carry from `lower` to `capacity`, as `feasible_flow` reads it
+    pub fn with_lower(lower: u32, capacity: u32) -> Self {
+        KarzanovArc {
+            lower,
+            ..Self::from_capacity(capacity)
+        }
+    }
+}
+
+impl<C: Capacity> KarzanovArc<C> {
+    /// an arc of any capacity type, e.g. `KarzanovArc::from_capacity(1u64 << 40)`
+    pub fn from_capacity(capacity: C) -> Self {
         KarzanovArc {
             capacity,
-            flow: 0,
+            flow: C::zero(),
             open: true,
+            locked: false,
+            lower: C::zero(),
+            twin: None,
         }
     }
+
+    pub fn capacity(&self) -> C {
+        self.capacity
+    }
+
+    pub fn flow(&self) -> C {
+        self.flow
+    }
+
+    pub fn lower(&self) -> C {
+        self.lower
+    }
+
+    /// whether the arc may take more flux in the current phase. a locked arc is never open,
+    /// and an arc into a node blocked in the last phase stays closed until the next solve
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
 }
 
-fn clean_network(network: &mut GraphNetwork<KarzanovNode, KarzanovArc>) {
-    for node in &mut network.node_data {
-        if let Some(node) = node {
-            node.stack.clear();
-            node.grouped = false;
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlowError {
+    /// the arc is live in only one of the networks
+    ArcMismatch(ArcId),
+    /// the arc would carry more than its capacity
+    CapacityExceeded(ArcId),
+    /// the arc would carry less than nothing
+    NegativeFlow(ArcId),
+    /// a second node with a net outgoing (or incoming) flux, so the flow is no longer a single s-t flow
+    Unbalanced(NodeId),
+}
+
+impl std::fmt::Display for FlowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlowError::ArcMismatch(arc_id) => {
+                write!(f, "arc {} is live in only one network", arc_id)
+            }
+            FlowError::CapacityExceeded(arc_id) => {
+                write!(f, "arc {} would exceed its capacity", arc_id)
+            }
+            FlowError::NegativeFlow(arc_id) => {
+                write!(f, "arc {} would carry a negative flow", arc_id)
+            }
+            FlowError::Unbalanced(node_id) => {
+                write!(f, "node {} breaks the conservation of the flow", node_id)
+            }
         }
     }
-    for arc in &mut network.arc_data {
-        if let Some(arc) = arc {
-            arc.flow = 0;
-            arc.open = true;
+}
+
+impl std::error::Error for FlowError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MaxFlowError {
+    /// the source or the sink is not in the network
+    NodeMissing(NodeId),
+    /// no path leads from the source to the sink
+    SinkUnreachable,
+    /// the flow to start from breaks a capacity or the conservation at the first arc or node given
+    InfeasibleFlow(FlowError),
+    /// the capacities leaving the source sum up to more than the capacity type holds, so a wider one is needed
+    CapacityOverflow(NodeId),
+    /// the solver found its own state broken at the arc or node given, which is a bug
+    InvariantViolation(FlowError),
+    /// `maxflow_acyclic` met a directed cycle through the node given on the way from the source to the sink
+    ContainsCycle(NodeId),
+    /// the passes went on past the ceiling of `nodes * arcs`, which a correct solve never reaches
+    DidNotConverge,
+}
+
+impl std::fmt::Display for MaxFlowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MaxFlowError::NodeMissing(node_id) => {
+                write!(f, "node {} does not exist", node_id)
+            }
+            MaxFlowError::SinkUnreachable => {
+                write!(f, "the sink is not reachable from the source")
+            }
+            MaxFlowError::InfeasibleFlow(error) => {
+                write!(f, "the flow to start from is infeasible: {}", error)
+            }
+            MaxFlowError::CapacityOverflow(node_id) => {
+                write!(f, "the capacities leaving node {} overflow", node_id)
+            }
+            MaxFlowError::InvariantViolation(error) => {
+                write!(f, "the solver broke an invariant: {}", error)
+            }
+            MaxFlowError::ContainsCycle(node_id) => {
+                write!(
+                    f,
+                    "node {} lies on a cycle between the source and the sink",
+                    node_id
+                )
+            }
+            MaxFlowError::DidNotConverge => {
+                write!(f, "the solver did not converge")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MaxFlowError {}
+
+/// what a solve did, as `maxflow_with_stats` counts it over all its phases
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaxFlowStats {
+    /// the passes of pushes, over the level graph and those of the residual networks
+    pub iterations: usize,
+    /// the passes of balancing
+    pub balancing_passes: usize,
+    /// the pushes which filled an arc up to its capacity
+    pub arcs_saturated: usize,
+    /// the flux pushed forward, summed up
+    pub flow_pushed: u64,
+    /// the flux blocked nodes returned, summed up
+    pub flow_returned: u64,
+}
+
+impl MaxFlowStats {
+    fn push<C: Capacity>(&mut self, delta: C, saturates: bool) {
+        self.flow_pushed = self.flow_pushed.saturating_add(delta.to_u64());
+        if saturates {
+            self.arcs_saturated += 1;
+        }
+    }
+}
+
+fn clean_network<C: Capacity>(network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>) {
+    for node in network.node_data.iter_mut().flatten() {
+        node.stack.clear();
+        node.layer = None;
+        node.blocked = false;
+    }
+    for arc in network.arc_data.iter_mut().flatten() {
+        if arc.locked {
+            arc.open = false;
+            continue;
         }
+        arc.flow = C::zero();
+        arc.open = true;
     }
 }
 
-fn grouping_nodes_by_layer(
+/// the nodes grouped by their breadth-first distance from the source into the layers of the level graph,
+/// whose arcs run from each layer to the next one, leaving the network as it is. the sink ends it alone:
+/// the other nodes at the distance of the sink or farther can never pass on their flux, so they are left out.
+/// zero-capacity arcs take no part
+pub fn layers<C: Capacity>(
     source_id: NodeId,
     sink_id: NodeId,
-    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
-) -> Vec<Vec<NodeId>> {
-    if !network.is_node_in(source_id) {
-        panic!("Node does not exist");
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<Vec<Vec<NodeId>>, MaxFlowError> {
+    for node_id in [source_id, sink_id] {
+        if !network.is_node_in(node_id) {
+            return Err(MaxFlowError::NodeMissing(node_id));
+        }
     }
-    // split into layers
+    let mut is_layered = vec![false; network.node_data.len()];
+    is_layered[source_id] = true;
     let mut layers: Vec<Vec<NodeId>> = vec![vec![source_id]];
-    loop {
+    while !is_layered[sink_id] {
         let mut next_layer: Vec<NodeId> = Vec::new();
         // collect nodes which is connected to the last layer into `next_layer`
         for node_id in layers.last().unwrap() {
-            let arcs: Vec<(NodeId, ArcId)> = network.from_node(node_id.clone()).collect();
-            for (dist_node_id, _) in arcs {
-                if network.data_of_node(dist_node_id).unwrap().grouped {
+            for (dist_node_id, arc_id) in network.from_node(*node_id) {
+                // a zero-capacity arc can never carry flux, so it defines no layer
+                if network.data_of_arc(arc_id).unwrap().capacity == C::zero()
+                    || is_layered[dist_node_id]
+                {
                     continue;
                 }
-                network.mut_data_of_node(dist_node_id).unwrap().grouped = true;
+                is_layered[dist_node_id] = true;
                 next_layer.push(dist_node_id);
             }
         }
-        // if there is no node to add, break
-        if next_layer.len() == 0 {
-            break;
+        // if there is no node to add, the sink is out of reach
+        if next_layer.is_empty() {
+            return Err(MaxFlowError::SinkUnreachable);
         }
         layers.push(next_layer);
     }
-    if layers.last().unwrap() != &vec![sink_id] {
-        panic!("this type of problem cannot be solved with karzanov's algorithm")
+    // leave the sink alone in the last layer
+    *layers.last_mut().unwrap() = vec![sink_id];
+    Ok(layers)
+}
+
+/// group the nodes into `layers` and write the layer of every node into it, or `None` if it has none
+fn grouping_nodes_by_layer<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<Vec<Vec<NodeId>>, MaxFlowError> {
+    let layers = layers(source_id, sink_id, network)?;
+    for node in network.node_data.iter_mut().flatten() {
+        node.layer = None;
     }
-    // sort the layers by the connection
-    // they should be ordered so that incoming-arc is calculated before the node is focused
-    for layer in layers.iter_mut() {
-        layer.sort_by(|a, b| {
-            let a_lt_b = network.is_arc_in(a.clone(), b.clone());
-            let b_lt_a = network.is_arc_in(b.clone(), a.clone());
-            match (a_lt_b, b_lt_a) {
-                (true, false) => std::cmp::Ordering::Less,
-                (false, true) => std::cmp::Ordering::Greater,
-                _ => std::cmp::Ordering::Equal,
-            }
-        });
+    for (d, layer) in layers.iter().enumerate() {
+        for node_id in layer {
+            network.mut_data_of_node(*node_id).unwrap().layer = Some(d);
+        }
     }
-    return layers;
+    Ok(layers)
 }
 
-fn incoming_flux_of_flow(
+/// whether the arc belongs to the level graph, running from a layer to the next one
+fn is_level_arc<C: Capacity>(
+    from_node_id: NodeId,
+    into_node_id: NodeId,
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> bool {
+    let layer_of = |node_id: NodeId| network.data_of_node(node_id).unwrap().layer;
+    match (layer_of(from_node_id), layer_of(into_node_id)) {
+        (Some(from_layer), Some(into_layer)) => from_layer + 1 == into_layer,
+        _ => false,
+    }
+}
+
+fn incoming_flux_of_flow<C: Capacity>(
     node_id: NodeId,
-    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
-) -> u32 {
-    let mut incoming_flux = 0;
-    for (_, arc_id) in network.into_node(node_id) {
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> C {
+    let mut incoming_flux = C::zero();
+    // a self-loop would count on both sides
+    for (_, arc_id) in network
+        .into_node(node_id)
+        .filter(|(other_id, _)| *other_id != node_id)
+    {
         let arc = network.data_of_arc(arc_id).unwrap();
-        incoming_flux += arc.flow;
+        incoming_flux = incoming_flux + arc.flow;
     }
-    return incoming_flux;
+    incoming_flux
 }
 
-fn outgoing_flux_of_flow(
+fn outgoing_flux_of_flow<C: Capacity>(
     node_id: NodeId,
-    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
-) -> u32 {
-    let mut outgoing_flux = 0;
-    for (_, arc_id) in network.from_node(node_id) {
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> C {
+    let mut outgoing_flux = C::zero();
+    // a self-loop would count on both sides
+    for (_, arc_id) in network
+        .from_node(node_id)
+        .filter(|(other_id, _)| *other_id != node_id)
+    {
         let arc = network.data_of_arc(arc_id).unwrap();
-        outgoing_flux += arc.flow;
+        outgoing_flux = outgoing_flux + arc.flow;
     }
-    return outgoing_flux;
+    outgoing_flux
+}
+
+/// the net flux leaving the source
+pub(crate) fn flow_value<C: Capacity>(
+    source_id: NodeId,
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> C {
+    outgoing_flux_of_flow(source_id, network) - incoming_flux_of_flow(source_id, network)
 }
 
-/// maximize outgoing fluxes of preflows
-fn maximize_outgoing(
-    layers: &Vec<Vec<NodeId>>,
+/// a node with its excess and the arcs to offer it to, in order
+type Offer<C> = (NodeId, C, Vec<(NodeId, ArcId)>);
+
+/// the pushes `(head, arc, delta)` of a node, and whether it overflows
+type Plan<C> = (Vec<(NodeId, ArcId, C)>, bool);
+
+/// maximize outgoing fluxes of preflows.
+/// every unblocked node from `start_layer` on pushes its excess along its open and unsaturated arcs,
+/// and a node which cannot push all of it is blocked.
+/// with `rng`, every node offers its flux to its arcs in a shuffled order,
+/// and with `parallel`, the nodes of a layer plan their pushes on the threads of rayon
+fn maximize_outgoing<C: Capacity>(
+    layers: &[Vec<NodeId>],
     mut start_layer: usize,
-    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
-) {
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+    mut rng: Option<&mut XorShift>,
+    parallel: bool,
+    stats: &mut MaxFlowStats,
+) -> Result<(), MaxFlowError> {
+    stats.iterations += 1;
     // saturate the first preflows
-    let source_node_id = layers.first().unwrap().first().unwrap().clone();
+    // (an arc into a blocked node is closed, and the flux it returned must stay with the source)
+    let source_node_id = *layers.first().unwrap().first().unwrap();
     let arcs: Vec<(NodeId, ArcId)> = network.from_node(source_node_id).collect();
     for (node_id, arc_id) in arcs {
+        if !is_level_arc(source_node_id, node_id, network) {
+            continue;
+        }
         let arc = network.mut_data_of_arc(arc_id).unwrap();
-        let capacity = arc.capacity;
-        let delta = capacity - arc.flow;
-        arc.flow = capacity;
-        let mut_node = network.mut_data_of_node(node_id).unwrap();
-        if delta > 0 {
-            mut_node.stack.push((arc_id, delta));
+        let delta = room_of(arc_id, arc)?;
+        if !arc.open || delta == C::zero() {
+            continue;
         }
+        arc.flow = arc.capacity;
+        stats.push(delta, true);
+        let mut_node = network.mut_data_of_node(node_id).unwrap();
+        mut_node.stack.push((arc_id, delta));
     }
-    // skip the first layer (== start node) / up to the start_layer
+    // skip the first layer (== start node) / up to the start_layer, and the last layer (== sink node)
     if start_layer < 1 {
         start_layer = 1;
     }
-    for layer in layers.iter().skip(start_layer) {
-        for node_id in layer {
-            let incoming_flux = incoming_flux_of_flow(node_id.clone(), network);
-            let mut consumed_flux = 0;
-
-            let arcs: Vec<(NodeId, ArcId)> = network.from_node(node_id.clone()).collect();
-
-            // collect consumed flux from closed or saturated arcs
-            for (_, arc_id) in arcs.clone() {
-                // passive assignments
-                let arc = network.data_of_arc(arc_id).unwrap();
-                if arc.open && arc.flow < arc.capacity {
-                    continue;
+    for layer in layers.iter().take(layers.len() - 1).skip(start_layer) {
+        // the nodes of a layer only read the arcs of the layer before, which are settled,
+        // and write their own outgoing arcs, so they plan their pushes independently
+        let excesses = map_nodes(layer, parallel, |node_id| {
+            let node = network.data_of_node(*node_id).unwrap();
+            let incoming_flux = incoming_flux_of_flow(*node_id, network);
+            let outgoing_flux = outgoing_flux_of_flow(*node_id, network);
+            let excess = incoming_flux.checked_sub(outgoing_flux).ok_or(
+                MaxFlowError::InvariantViolation(FlowError::Unbalanced(*node_id)),
+            )?;
+            Ok((!node.blocked && excess > C::zero()).then_some((*node_id, excess)))
+        });
+        // the shuffles draw from `rng` node by node, in the order of the layer
+        let offers: Vec<Offer<C>> = excesses
+            .into_iter()
+            .collect::<Result<Vec<_>, MaxFlowError>>()?
+            .into_iter()
+            .flatten()
+            .map(|(node_id, excess)| {
+                let mut arcs: Vec<(NodeId, ArcId)> = network.from_node(node_id).collect();
+                if let Some(rng) = rng.as_deref_mut() {
+                    rng.shuffle(&mut arcs);
                 }
-                // if closed or saturated
-                consumed_flux += arc.flow;
-                // make no assignment because the closed arc always has the identical flow and preflow
-            }
+                (node_id, excess, arcs)
+            })
+            .collect();
+        let plans = map_nodes(&offers, parallel, |(node_id, excess, arcs)| {
+            plan_outgoing(*node_id, *excess, arcs, network)
+        });
 
-            // distribute flux to the remaining arcs
-            for (node_id, arc_id) in arcs {
-                let arc = network.data_of_arc(arc_id).unwrap();
-                let capacity = arc.capacity;
-                if !arc.open || arc.flow >= capacity {
-                    continue;
-                }
-                // if open and unsaturated
-                let available_flux = incoming_flux - consumed_flux;
-                if available_flux <= 0 {
-                    // passive assignment
-                    let arc = network.mut_data_of_arc(arc_id).unwrap();
-                    arc.flow = 0;
-                } else {
-                    // active assignment
-                    // assign flux as much as capacity allows
-                    let arc = network.mut_data_of_arc(arc_id).unwrap();
-                    let preflow = min(capacity, available_flux);
-                    let delta = preflow - arc.flow;
-                    // there is no need to keep flow now
-                    arc.flow = preflow;
-                    consumed_flux += preflow;
-                    if delta > 0 {
-                        let mut_node = network.mut_data_of_node(node_id).unwrap();
-                        mut_node.stack.push((arc_id, delta));
-                    }
+        let plans = plans.into_iter().collect::<Result<Vec<_>, _>>()?;
+        for ((node_id, _, _), (pushes, overflows)) in offers.iter().zip(plans) {
+            for (dist_node_id, arc_id, delta) in pushes {
+                let arc = network.mut_data_of_arc(arc_id).unwrap();
+                arc.flow = arc.flow + delta;
+                stats.push(delta, arc.flow == arc.capacity);
+                let mut_node = network.mut_data_of_node(dist_node_id).unwrap();
+                mut_node.stack.push((arc_id, delta));
+            }
+            if overflows {
+                // it overflows, so block it: the incoming arcs are closed for good
+                // and the next balancing returns the excess
+                network.mut_data_of_node(*node_id).unwrap().blocked = true;
+                let arcs: Vec<(NodeId, ArcId)> = network.into_node(*node_id).collect();
+                for (_, arc_id) in arcs {
+                    network.mut_data_of_arc(arc_id).unwrap().open = false;
                 }
             }
         }
     }
+    Ok(())
 }
 
-/// balance incoming fluxes of preflows
-/// return new s (= start_layer) and update the network
-fn balance_incoming(
-    layers: &Vec<Vec<NodeId>>,
-    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
-) -> Option<usize> {
-    // search for the last deficient layer
-    let mut last_deficient_layer: Option<usize> = None;
+/// how much more the arc takes, or the violation if it already carries more than its capacity
+fn room_of<C: Capacity>(arc_id: ArcId, arc: &KarzanovArc<C>) -> Result<C, MaxFlowError> {
+    arc.capacity
+        .checked_sub(arc.flow)
+        .ok_or(MaxFlowError::InvariantViolation(
+            FlowError::CapacityExceeded(arc_id),
+        ))
+}
+
+/// the pushes `(head, arc, delta)` of the excess of a node along `arcs`, in their order,
+/// and whether some of the excess is left, so that the node overflows
+fn plan_outgoing<C: Capacity>(
+    node_id: NodeId,
+    mut excess: C,
+    arcs: &[(NodeId, ArcId)],
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<Plan<C>, MaxFlowError> {
+    let mut pushes = Vec::new();
+    for (dist_node_id, arc_id) in arcs {
+        if !is_level_arc(node_id, *dist_node_id, network) {
+            continue;
+        }
+        let arc = network.data_of_arc(*arc_id).unwrap();
+        let room = room_of(*arc_id, arc)?;
+        // a closed, zero-capacity or saturated arc takes no more
+        if !arc.open || room == C::zero() {
+            continue;
+        }
+        // assign flux as much as capacity allows
+        let delta = min(room, excess);
+        excess = excess - delta;
+        pushes.push((*dist_node_id, *arc_id, delta));
+        if excess == C::zero() {
+            break;
+        }
+    }
+    Ok((pushes, excess > C::zero()))
+}
+
+/// apply `f` to every item, on the threads of rayon if `parallel` and the `rayon` feature is on.
+/// the results keep the order of the items either way
+#[cfg(feature = "rayon")]
+fn map_nodes<T: Sync, R: Send>(
+    items: &[T],
+    parallel: bool,
+    f: impl Fn(&T) -> R + Sync + Send,
+) -> Vec<R> {
+    use rayon::prelude::*;
+
+    if parallel {
+        items.par_iter().map(f).collect()
+    } else {
+        items.iter().map(f).collect()
+    }
+}
+
+#[cfg(not(feature = "rayon"))]
+fn map_nodes<T, R>(items: &[T], _parallel: bool, f: impl Fn(&T) -> R) -> Vec<R> {
+    items.iter().map(f).collect()
+}
+
+/// balance incoming fluxes of preflows.
+/// every blocked node returns its excess by popping its stack, from the last layer down,
+/// so that the flux a blocked node gets back is returned in the same pass.
+/// return new s (= start_layer), the first layer holding returned flux of an unblocked node,
+/// and update the network
+fn balance_incoming<C: Capacity>(
+    layers: &[Vec<NodeId>],
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+    stats: &mut MaxFlowStats,
+) -> Result<Option<usize>, MaxFlowError> {
+    stats.balancing_passes += 1;
+    let mut first_returned_layer: Option<usize> = None;
     // skip the last layer (== sink node) and the first layer (== source node)
-    for (d, layer) in layers.iter().enumerate().skip(1).rev().skip(1) {
-        // is the node deficient?
-        for node_id in layer {
-            let outgoing_flux = outgoing_flux_of_flow(node_id.clone(), network);
-            let mut incoming_flux = incoming_flux_of_flow(node_id.clone(), network);
+    // watch out: this is a reverse iteration, also inside a layer
+    for layer in layers.iter().skip(1).rev().skip(1) {
+        for node_id in layer.iter().rev() {
+            let outgoing_flux = outgoing_flux_of_flow(*node_id, network);
+            let mut incoming_flux = incoming_flux_of_flow(*node_id, network);
             if incoming_flux == outgoing_flux {
                 // it is not deficient
                 continue;
             }
+            let unbalanced = MaxFlowError::InvariantViolation(FlowError::Unbalanced(*node_id));
             if incoming_flux < outgoing_flux {
-                panic!("this situation cannot be occured. something went wrong!!")
+                return Err(unbalanced);
             }
             // it is deficient
-            if last_deficient_layer.is_none() {
-                // memorize the last deficient layer
-                // watch out: this is a reverse iteration
-                last_deficient_layer = Some(d);
+            if !network.data_of_node(*node_id).unwrap().blocked {
+                // it got flux back in this pass, and pushes it on in the next one
+                continue;
             }
 
-            loop {
-                if incoming_flux <= outgoing_flux {
-                    // it is finally balanced
-                    break;
-                }
-                let node = network.mut_data_of_node(node_id.clone()).unwrap();
+            while incoming_flux > outgoing_flux {
+                let node = network.mut_data_of_node(*node_id).unwrap();
                 // pop the stack and decrease the flow based on it
                 // `delta` is an amount of the flow (of an arc of the arc_id) was increased at once
-                if let Some((arc_id, delta)) = node.stack.pop() {
-                    let arc = network.mut_data_of_arc(arc_id).unwrap();
-                    // if the flow is decreased by `max_decrease`, the incoming_flux coincides with the outgoing_flux
-                    let max_decrease = incoming_flux - outgoing_flux;
-                    arc.flow -= min(delta, max_decrease);
-                    incoming_flux -= min(delta, max_decrease);
-                } else {
-                    panic!("this situation cannot be occured. something went wrong!!")
+                let Some((arc_id, delta)) = node.stack.pop() else {
+                    return Err(unbalanced);
+                };
+                // if the flow is decreased by `max_decrease`, the incoming_flux coincides with the outgoing_flux
+                let max_decrease = incoming_flux - outgoing_flux;
+                let decrease = min(delta, max_decrease);
+                if decrease < delta {
+                    // the rest of the push stays, and may be returned later
+                    node.stack.push((arc_id, delta - decrease));
                 }
-            }
-
-            // close the arcs which hit the `over-incoming` state. (and it's balanced now)
-            // if the arc's flow were increased, the node overflows again.
-            let arcs: Vec<(NodeId, ArcId)> = network.into_node(node_id.clone()).collect();
-            for (_, arc_id) in arcs {
                 let arc = network.mut_data_of_arc(arc_id).unwrap();
-                arc.open = false;
+                arc.flow =
+                    arc.flow
+                        .checked_sub(decrease)
+                        .ok_or(MaxFlowError::InvariantViolation(FlowError::NegativeFlow(
+                            arc_id,
+                        )))?;
+                incoming_flux = incoming_flux - decrease;
+                stats.flow_returned = stats.flow_returned.saturating_add(decrease.to_u64());
+
+                let (from_node_id, _) = network.arc_endpoints(arc_id).unwrap();
+                let from_node = network.data_of_node(from_node_id).unwrap();
+                let from_layer = from_node.layer.unwrap();
+                if from_layer > 0 && !from_node.blocked {
+                    first_returned_layer =
+                        Some(first_returned_layer.map_or(from_layer, |d| d.min(from_layer)));
+                }
             }
         }
     }
 
-    // start with d-1 th layer. re-distribution or overflow-propagation maybe occur in d-1 th layer
-    if let Some(d) = last_deficient_layer {
-        return Some(d - 1);
-    } else {
-        return None;
+    if is_balanced(layers, network) {
+        return Ok(None);
     }
+    // without an unblocked node to push again, the next pass of pushes only re-saturates the source arcs
+    Ok(Some(first_returned_layer.unwrap_or(layers.len() - 1)))
+}
+
+/// whether every node between the first and the last layers has equal incoming and outgoing fluxes
+fn is_balanced<C: Capacity>(
+    layers: &[Vec<NodeId>],
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> bool {
+    layers
+        .iter()
+        .skip(1)
+        .rev()
+        .skip(1)
+        .flatten()
+        .all(|node_id| {
+            incoming_flux_of_flow(*node_id, network) == outgoing_flux_of_flow(*node_id, network)
+        })
 }
 
-pub fn maxflow(
+/// run the phases until the preflow settles, and return the number of phases.
+/// the settled flow is blocking: every path of the layers holds a saturated arc
+fn run_phases<C: Capacity>(
     source_id: NodeId,
     sink_id: NodeId,
-    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
-) {
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+    mut rng: Option<&mut XorShift>,
+    parallel: bool,
+    stats: &mut MaxFlowStats,
+) -> Result<usize, MaxFlowError> {
     clean_network(network);
-    let layers = grouping_nodes_by_layer(source_id, sink_id, network);
+    let layers = grouping_nodes_by_layer(source_id, sink_id, network)?;
     let mut start_layer = 0;
-    let mut flow_snapshot = HashMap::<NodeId, u32>::new();
+    // no flow can exceed what leaves the source or what enters the sink.
+    // every flux of a node came from the source, so no sum of fluxes overflows if the first one fits
+    let capacity_sum = |arcs: &mut dyn Iterator<Item = (NodeId, ArcId)>| {
+        arcs.map(|(_, arc_id)| network.data_of_arc(arc_id).unwrap().capacity)
+            .try_fold(C::zero(), |sum, capacity| sum.checked_add(capacity))
+    };
+    let source_capacity = capacity_sum(&mut network.from_node(source_id))
+        .ok_or(MaxFlowError::CapacityOverflow(source_id))?;
+    let upper_bound = match capacity_sum(&mut network.into_node(sink_id)) {
+        Some(sink_capacity) => min(source_capacity, sink_capacity),
+        None => source_capacity,
+    };
+    let mut phases = 0;
+    let ceiling = iteration_ceiling(network);
 
     loop {
-        maximize_outgoing(&layers, start_layer, network);
-        let new_start_layer = balance_incoming(&layers, network);
-        if new_start_layer.is_none() {
-            break;
+        phases += 1;
+        if phases > ceiling {
+            return Err(MaxFlowError::DidNotConverge);
         }
-        start_layer = new_start_layer.unwrap();
+        maximize_outgoing(
+            &layers,
+            start_layer,
+            network,
+            rng.as_deref_mut(),
+            parallel,
+            stats,
+        )?;
 
-        // compare with the snapshot
-        let mut different = false;
-        for (arc_id, arc) in network.arc_data.iter().enumerate() {
-            if let Some(arc) = arc {
-                if flow_snapshot
-                    .get(&arc_id)
-                    .is_none_or(|snapshot| &arc.flow != snapshot)
-                {
-                    different = true;
-                    break;
-                }
+        // a flow which hits the upper bound is already maximum, so every excess just goes back
+        if incoming_flux_of_flow(sink_id, network) == upper_bound {
+            for node in network.node_data.iter_mut().flatten() {
+                node.blocked = true;
             }
-        }
-        if !different {
+            while balance_incoming(&layers, network, stats)?.is_some() {}
             break;
         }
-        // take a snapshot of the flow
-        for (arc_id, arc) in network.arc_data.iter().enumerate() {
-            if let Some(arc) = arc {
-                flow_snapshot.insert(arc_id, arc.flow);
-            }
+
+        match balance_incoming(&layers, network, stats)? {
+            Some(new_start_layer) => start_layer = new_start_layer,
+            None => break,
         }
     }
+    debug_assert!(network.all_nodes_balanced(source_id, sink_id));
+    Ok(phases)
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
+/// the most passes of pushes a blocking flow, or level graphs a solve, may take before it is taken as stuck.
+/// either takes at most one per node, so this leaves plenty of room
+fn iteration_ceiling<C: Capacity>(
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> usize {
+    (network.node_data.len() + 1) * (network.arc_data.len() + 1)
+}
 
-    use super::*;
+type ResidualOrigins<C> = (
+    GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+    Vec<(ArcId, bool)>,
+);
 
-    /// source, sink, network
-    pub fn make_network_instance() -> (NodeId, NodeId, GraphNetwork<KarzanovNode, KarzanovArc>) {
-        let mut network = GraphNetwork::new();
-        network.add_nodes(vec![KarzanovNode::new(); 9].into_iter());
-        network.bulk_connect(
-            vec![
-                (0, 1, KarzanovArc::new(1)),
-                (0, 3, KarzanovArc::new(8)),
-                (1, 2, KarzanovArc::new(2)),
-                (1, 4, KarzanovArc::new(1)),
-                (2, 5, KarzanovArc::new(1)),
-                (3, 1, KarzanovArc::new(4)),
-                (3, 4, KarzanovArc::new(2)),
-                (3, 6, KarzanovArc::new(4)),
-                (4, 5, KarzanovArc::new(3)),
-                (5, 8, KarzanovArc::new(4)),
-                (6, 7, KarzanovArc::new(2)),
-                (6, 5, KarzanovArc::new(1)),
-                (7, 8, KarzanovArc::new(2)),
-            ]
-            .into_iter(),
-        );
-        return (0, 8, network);
+/// the residual network as a network of its own under the same node ids, the arcs of which map to
+/// `(arc, whether it is used forward)`. locked arcs take no part
+fn residual_with_origins<C: Capacity>(
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> ResidualOrigins<C> {
+    let mut residual = GraphNetwork::new();
+    residual.add_nodes((0..network.node_data.len()).map(|_| KarzanovNode::default()));
+    let mut origins = Vec::new();
+    for (arc_id, arc) in network.arc_data.iter().enumerate() {
+        let (Some(arc), Some((from, into))) = (arc, network.arc_endpoints(arc_id)) else {
+            continue;
+        };
+        if arc.locked || from == into {
+            continue;
+        }
+        if arc.flow < arc.capacity {
+            residual.connect(
+                from,
+                into,
+                KarzanovArc::from_capacity(arc.capacity - arc.flow),
+            );
+            origins.push((arc_id, true));
+        }
+        if arc.flow > C::zero() {
+            residual.connect(into, from, KarzanovArc::from_capacity(arc.flow));
+            origins.push((arc_id, false));
+        }
     }
+    (residual, origins)
+}
 
-    #[test]
-    fn karzanov() {
-        let (source_id, sink_id, mut network) = make_network_instance();
-        let layers = grouping_nodes_by_layer(source_id, sink_id, &mut network);
-        println!("Network: {:?}", network);
-        println!("Layers: {:?}", layers);
-        let mut start_layer = 0;
-        let mut flow_snapshot = HashMap::<NodeId, u32>::new();
-
-        loop {
-            println!("===compleation===");
-            maximize_outgoing(&layers, start_layer, &mut network);
-            println!("Network: {:?}", network);
-            println!("===balancing===");
-            let new_start_layer = balance_incoming(&layers, &mut network);
-            println!("Network: {:?}", network);
+/// solve the network: a blocking flow of its level graph, then blocking flows of the level graphs of
+/// the residual network until the sink is out of reach, as Dinic's algorithm does with its phases.
+/// a single blocking flow is not always maximum, as it never takes back a push
+fn solve<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+    mut rng: Option<&mut XorShift>,
+    parallel: bool,
+    stats: &mut MaxFlowStats,
+) -> Result<(), MaxFlowError> {
+    if source_id == sink_id {
+        // nothing has to go anywhere, so the flow is zero
+        layers(source_id, sink_id, network)?;
+        clean_network(network);
+        return Ok(());
+    }
+    if network.arc_data.iter().flatten().any(|arc| arc.locked) {
+        // the phases would take the committed flows for excesses, so they only raise a flow around them
+        layers(source_id, sink_id, network)?;
+        clean_network(network);
+        route_locked_flows(source_id, sink_id, network)?;
+    } else {
+        run_phases(
+            source_id,
+            sink_id,
+            network,
+            rng.as_deref_mut(),
+            parallel,
+            stats,
+        )?;
+    }
+    augment(source_id, sink_id, network, rng, parallel, stats)?;
+    cancel_twins(network);
+    Ok(())
+}
 
-            if new_start_layer.is_none() {
-                println!("nothing to be balanced");
-                break;
+/// give the other arcs of a clean network a flow which balances the flows of the locked arcs,
+/// as a locked arc must pass on at its head what its tail receives. every locked arc `u -> v` of flow `f`
+/// hangs `v` on a super-source and `u` on a super-sink by arcs of `f`, and what reaches the sink
+/// may come back to the source. fail with `InfeasibleFlow` at a node which cannot receive what its locked arcs send
+fn route_locked_flows<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<(), MaxFlowError> {
+    let mut auxiliary = GraphNetwork::new();
+    auxiliary.add_nodes((0..network.node_data.len()).map(|_| KarzanovNode::default()));
+    let mut origins = Vec::new();
+    let mut locked_flows = Vec::new();
+    for (arc_id, arc) in network.arc_data.iter().enumerate() {
+        let (Some(arc), Some((from, into))) = (arc, network.arc_endpoints(arc_id)) else {
+            continue;
+        };
+        if arc.locked {
+            if arc.flow > C::zero() && from != into {
+                locked_flows.push((from, into, arc.flow));
             }
-            start_layer = new_start_layer.unwrap();
+        } else {
+            let auxiliary_arc_id =
+                auxiliary.connect(from, into, KarzanovArc::from_capacity(arc.capacity));
+            origins.push((arc_id, auxiliary_arc_id));
+        }
+    }
+    if locked_flows.is_empty() {
+        return Ok(());
+    }
+    // no more than the locked flows in total goes around
+    let total = locked_flows
+        .iter()
+        .try_fold(C::zero(), |sum, (_, _, flow)| sum.checked_add(*flow))
+        .ok_or(MaxFlowError::CapacityOverflow(source_id))?;
+    auxiliary.connect(sink_id, source_id, KarzanovArc::from_capacity(total));
+    let super_source_id = auxiliary.add_node(KarzanovNode::default());
+    let super_sink_id = auxiliary.add_node(KarzanovNode::default());
+    let demands: Vec<(NodeId, ArcId)> = locked_flows
+        .into_iter()
+        .map(|(from, into, flow)| {
+            auxiliary.connect(super_source_id, into, KarzanovArc::from_capacity(flow));
+            let demand = auxiliary.connect(from, super_sink_id, KarzanovArc::from_capacity(flow));
+            (from, demand)
+        })
+        .collect();
+    match maxflow(super_source_id, super_sink_id, &mut auxiliary) {
+        Ok(_) | Err(MaxFlowError::SinkUnreachable) => {}
+        Err(error) => return Err(error),
+    }
+    for (node_id, demand) in demands {
+        let arc = auxiliary.data_of_arc(demand).unwrap();
+        if arc.flow < arc.capacity {
+            return Err(MaxFlowError::InfeasibleFlow(FlowError::Unbalanced(node_id)));
+        }
+    }
+    for (arc_id, auxiliary_arc_id) in origins {
+        network.mut_data_of_arc(arc_id).unwrap().flow =
+            auxiliary.data_of_arc(auxiliary_arc_id).unwrap().flow;
+    }
+    Ok(())
+}
 
-            // compare with the snapshot
-            let mut different = false;
-            for (arc_id, arc) in network.arc_data.iter().enumerate() {
-                if let Some(arc) = arc {
-                    if flow_snapshot
-                        .get(&arc_id)
-                        .is_none_or(|snapshot| &arc.flow != snapshot)
-                    {
-                        println!("{:?} != {:?}", flow_snapshot.get(&arc_id), &arc.flow);
-                        different = true;
-                        break;
-                    }
-                    println!("{:?} == {:?}", flow_snapshot.get(&arc_id), &arc.flow)
-                }
-            }
-            if !different {
-                println!("no change");
-                break;
+/// let only one arc of every undirected edge carry flow, the net amount, as the flow both ways
+/// carry in common goes around in a circle. a twin whose arc no longer runs the other way is ignored
+fn cancel_twins<C: Capacity>(network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>) {
+    for arc_id in 0..network.arc_data.len() {
+        let Some(twin_id) = network.data_of_arc(arc_id).and_then(|arc| arc.twin) else {
+            continue;
+        };
+        let (Some((from, into)), Some(twin_endpoints)) = (
+            network.arc_endpoints(arc_id),
+            network.arc_endpoints(twin_id),
+        ) else {
+            continue;
+        };
+        if twin_endpoints != (into, from) {
+            continue;
+        }
+        let common = min(
+            network.data_of_arc(arc_id).unwrap().flow,
+            network.data_of_arc(twin_id).unwrap().flow,
+        );
+        for id in [arc_id, twin_id] {
+            let arc = network.mut_data_of_arc(id).unwrap();
+            arc.flow = arc.flow - common;
+        }
+    }
+}
+
+/// raise the flow the network already carries with blocking flows of the level graphs of the residual network,
+/// until the sink is out of reach. the flow must respect the capacities and be balanced
+fn augment<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+    mut rng: Option<&mut XorShift>,
+    parallel: bool,
+    stats: &mut MaxFlowStats,
+) -> Result<(), MaxFlowError> {
+    let ceiling = iteration_ceiling(network);
+    for round in 1.. {
+        if round > ceiling {
+            return Err(MaxFlowError::DidNotConverge);
+        }
+        let (mut residual, origins) = residual_with_origins(network);
+        match run_phases(
+            source_id,
+            sink_id,
+            &mut residual,
+            rng.as_deref_mut(),
+            parallel,
+            stats,
+        ) {
+            Err(MaxFlowError::SinkUnreachable) => break,
+            result => result?,
+        };
+        if flow_value(source_id, &residual) == C::zero() {
+            // never happens with a blocking flow, but never loop forever either
+            break;
+        }
+        for (residual_arc_id, (arc_id, is_forward)) in origins.into_iter().enumerate() {
+            let delta = residual.data_of_arc(residual_arc_id).unwrap().flow;
+            let arc = network.mut_data_of_arc(arc_id).unwrap();
+            if is_forward {
+                arc.flow = arc.flow + delta;
+            } else {
+                arc.flow = arc.flow - delta;
             }
-            // take a snapshot of the flow
-            for (arc_id, arc) in network.arc_data.iter().enumerate() {
-                if let Some(arc) = arc {
-                    flow_snapshot.insert(arc_id, arc.flow);
+        }
+    }
+    Ok(())
+}
+
+/// solve the network, overwriting the flows, and return the flow value.
+/// fail if the source or the sink is missing, or the sink is out of reach.
+/// a self-loop may stay in the network, but never joins two layers, so it carries no flow,
+/// and a source which is the sink too sends none. a directed cycle is solved like any other arcs,
+/// as the residual phases take back what a level graph pushed wrongly; `maxflow_acyclic` rejects one instead
+pub fn maxflow<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<C, MaxFlowError> {
+    maxflow_with_stats(source_id, sink_id, network).map(|(value, _)| value)
+}
+
+/// solve the network like `maxflow`, but fail with `ContainsCycle` first if a directed cycle lies on the way
+/// from the source to the sink, as karzanov's original algorithm asks for an acyclic network.
+/// `maxflow` itself solves cyclic networks through its residual phases
+pub fn maxflow_acyclic<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<C, MaxFlowError> {
+    for node_id in [source_id, sink_id] {
+        if !network.is_node_in(node_id) {
+            return Err(MaxFlowError::NodeMissing(node_id));
+        }
+    }
+    if let Some(node_id) = network.find_cycle_between(source_id, sink_id) {
+        return Err(MaxFlowError::ContainsCycle(node_id));
+    }
+    maxflow(source_id, sink_id, network)
+}
+
+/// solve the network like `maxflow`, and return the flow value with what the solve did
+pub fn maxflow_with_stats<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<(C, MaxFlowStats), MaxFlowError> {
+    let mut stats = MaxFlowStats::default();
+    solve(source_id, sink_id, network, None, false, &mut stats)?;
+    let value = flow_value(source_id, network);
+    debug_assert_eq!(value, incoming_flux_of_flow(sink_id, network));
+    Ok((value, stats))
+}
+
+/// solve the network like `maxflow`, but let every node offer its flux to its arcs in an order
+/// drawn from `seed`, and return the flow value. the same seed gives the same flow,
+/// while other seeds may pick other maximum flows, e.g. to study how the load spreads
+pub fn maxflow_seeded<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+    seed: u64,
+) -> Result<C, MaxFlowError> {
+    let rng = Some(&mut XorShift::new(seed));
+    solve(
+        source_id,
+        sink_id,
+        network,
+        rng,
+        false,
+        &mut MaxFlowStats::default(),
+    )?;
+    Ok(flow_value(source_id, network))
+}
+
+/// solve the network like `maxflow`, but let the nodes of a layer plan their pushes on the threads of rayon.
+/// the flow is the one `maxflow` finds, so only wide layers make it worth the threads
+#[cfg(feature = "rayon")]
+pub fn maxflow_parallel<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<C, MaxFlowError> {
+    solve(
+        source_id,
+        sink_id,
+        network,
+        None,
+        true,
+        &mut MaxFlowStats::default(),
+    )?;
+    Ok(flow_value(source_id, network))
+}
+
+/// solve any network, overwriting the flows, and return the flow value, which is zero if the sink is out of reach.
+/// karzanov's phases give a blocking flow of the level graph, and further phases run on the level graphs
+/// of the residual network until the sink falls out of reach, so cycles and arcs skipping layers are handled.
+/// fail as `maxflow` does, but for a sink out of reach
+pub fn maxflow_general<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<C, MaxFlowError> {
+    match maxflow(source_id, sink_id, network) {
+        Err(MaxFlowError::SinkUnreachable) => Ok(C::zero()),
+        result => result,
+    }
+}
+
+/// set the capacity of a live arc, keeping its flow, and return the old capacity.
+/// `None` (and nothing changes) if the arc is removed or out of range. solve again with `maxflow_incremental`
+pub fn set_capacity(
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+    arc_id: ArcId,
+    capacity: u32,
+) -> Option<u32> {
+    let arc = network.arc_data.get_mut(arc_id)?.as_mut()?;
+    Some(std::mem::replace(&mut arc.capacity, capacity))
+}
+
+/// solve the network starting from the flow it already carries, and return the flow value.
+/// the phases only add to that flow on the residual network, so a solve after a small edit is quick.
+/// fail with `InfeasibleFlow` if the flow breaks a capacity or the conservation, leaving the network as it is
+pub fn maxflow_warmstart<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<C, MaxFlowError> {
+    for node_id in [source_id, sink_id] {
+        if !network.is_node_in(node_id) {
+            return Err(MaxFlowError::NodeMissing(node_id));
+        }
+    }
+    if let Err(arc_ids) = validate_capacities(network) {
+        return Err(MaxFlowError::InfeasibleFlow(FlowError::CapacityExceeded(
+            arc_ids[0],
+        )));
+    }
+    if let Err(node_ids) = validate_flow(source_id, sink_id, network) {
+        return Err(MaxFlowError::InfeasibleFlow(FlowError::Unbalanced(
+            node_ids[0],
+        )));
+    }
+    augment(
+        source_id,
+        sink_id,
+        network,
+        None,
+        false,
+        &mut MaxFlowStats::default(),
+    )?;
+    cancel_twins(network);
+    Ok(flow_value(source_id, network))
+}
+
+/// solve a network again after its capacities changed, and return the flow value.
+/// while the flow it carries still fits, it warm-starts as `maxflow_warmstart`, so an increase costs
+/// only the residual phases it opens up, and a capacity below its flow starts over as `maxflow`.
+/// no phase restarts at the layer of the changed arc through `start_layer`:
+/// a finished solve spans the level graphs of several residual networks, so no layer of one of them is left to restart from
+pub fn maxflow_incremental(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Result<u32, MaxFlowError> {
+    match maxflow_warmstart(source_id, sink_id, network) {
+        Err(MaxFlowError::InfeasibleFlow(_)) => maxflow(source_id, sink_id, network),
+        result => result,
+    }
+}
+
+/// find a flow from the source to the sink in which every arc carries at least its lower bound,
+/// write it into the network and return its value, or `None` if there is none, e.g. as a lower bound
+/// exceeds its capacity, or if its value does not fit in u32. the flow is feasible, not maximum.
+/// the other solves ignore the lower bounds
+pub fn feasible_flow(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Option<u32> {
+    if !network.is_node_in(source_id) || !network.is_node_in(sink_id) {
+        return None;
+    }
+    // the lower bounds go in advance, and an arc keeps the room above its bound.
+    // the demands and the flow coming back may sum up past u32, so the auxiliary network counts in u64
+    let mut auxiliary: GraphNetwork<KarzanovNode<u64>, KarzanovArc<u64>> = GraphNetwork::new();
+    auxiliary.add_nodes((0..network.node_data.len()).map(|_| KarzanovNode::default()));
+    let mut demands = vec![0i64; network.node_data.len()];
+    let mut origins = Vec::new();
+    for (arc_id, arc) in network.arc_data.iter().enumerate() {
+        let (Some(arc), Some((from, into))) = (arc, network.arc_endpoints(arc_id)) else {
+            continue;
+        };
+        if arc.lower > arc.capacity {
+            return None;
+        }
+        demands[into] += arc.lower as i64;
+        demands[from] -= arc.lower as i64;
+        let room = auxiliary.connect(
+            from,
+            into,
+            KarzanovArc::from_capacity((arc.capacity - arc.lower) as u64),
+        );
+        origins.push((arc_id, room));
+    }
+    // what reaches the sink may come back to the source, and the demands hang on a super-source and sink
+    let total_capacity: u64 = network
+        .arc_data
+        .iter()
+        .flatten()
+        .map(|arc| arc.capacity as u64)
+        .sum();
+    let back_arc_id = auxiliary.connect(
+        sink_id,
+        source_id,
+        KarzanovArc::from_capacity(total_capacity),
+    );
+    let super_source_id = auxiliary.add_node(KarzanovNode::default());
+    let super_sink_id = auxiliary.add_node(KarzanovNode::default());
+    let mut required = 0;
+    for (node_id, demand) in demands.into_iter().enumerate() {
+        let demand_capacity = KarzanovArc::from_capacity(demand.unsigned_abs());
+        if demand > 0 {
+            auxiliary.connect(super_source_id, node_id, demand_capacity);
+            required += demand as u64;
+        } else if demand < 0 {
+            auxiliary.connect(node_id, super_sink_id, demand_capacity);
+        }
+    }
+    let supplied = maxflow_general(super_source_id, super_sink_id, &mut auxiliary).ok()?;
+    if supplied < required {
+        return None;
+    }
+    // what comes back to the source is the flow value, which has to fit in u32 as well
+    u32::try_from(auxiliary.data_of_arc(back_arc_id).unwrap().flow).ok()?;
+    for (arc_id, room) in origins {
+        let arc = network.mut_data_of_arc(arc_id).unwrap();
+        arc.flow = arc.lower + auxiliary.data_of_arc(room).unwrap().flow as u32;
+    }
+    Some(flow_value(source_id, network))
+}
+
+/// check the conservation of the flow: every live node but the source and the sink must have equal
+/// incoming and outgoing fluxes. fail with the nodes which do not, in the order of their ids
+pub fn validate_flow<C: Capacity>(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<(), Vec<NodeId>> {
+    let unbalanced: Vec<NodeId> = (0..network.node_data.len())
+        .filter(|node_id| *node_id != source_id && *node_id != sink_id)
+        .filter(|node_id| network.is_node_in(*node_id))
+        .filter(|node_id| {
+            incoming_flux_of_flow(*node_id, network) != outgoing_flux_of_flow(*node_id, network)
+        })
+        .collect();
+    if unbalanced.is_empty() {
+        Ok(())
+    } else {
+        Err(unbalanced)
+    }
+}
+
+/// check that no live arc carries more than its capacity. fail with the arcs which do, in the order of their ids
+pub fn validate_capacities<C: Capacity>(
+    network: &GraphNetwork<KarzanovNode<C>, KarzanovArc<C>>,
+) -> Result<(), Vec<ArcId>> {
+    let exceeding: Vec<ArcId> = network
+        .arc_data
+        .iter()
+        .enumerate()
+        .filter_map(|(arc_id, arc)| arc.as_ref().map(|arc| (arc_id, arc)))
+        .filter(|(_, arc)| arc.flow > arc.capacity)
+        .map(|(arc_id, _)| arc_id)
+        .collect();
+    if exceeding.is_empty() {
+        Ok(())
+    } else {
+        Err(exceeding)
+    }
+}
+
+impl<C: Capacity> GraphNetwork<KarzanovNode<C>, KarzanovArc<C>> {
+    /// whether every node but the source and the sink has equal incoming and outgoing fluxes.
+    /// only meaningful once the solve has converged, as a preflow mid-solve may hold excesses
+    pub fn all_nodes_balanced(&self, source_id: NodeId, sink_id: NodeId) -> bool {
+        validate_flow(source_id, sink_id, self).is_ok()
+    }
+
+    /// how much more a live arc can carry, `None` if it is removed or out of range.
+    /// an arc carrying more than its capacity, as `set_capacity` may leave it, has no room
+    pub fn residual(&self, arc_id: ArcId) -> Option<C> {
+        let arc = self.arc_data.get(arc_id)?.as_ref()?;
+        Some(arc.capacity.checked_sub(arc.flow).unwrap_or(C::zero()))
+    }
+}
+
+/// the size of a network, as `GraphNetwork::stats` gives it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphStats {
+    /// the live nodes
+    pub nodes: usize,
+    /// the live arcs
+    pub arcs: usize,
+    /// the capacities of the live arcs summed up
+    pub total_capacity: u64,
+    /// the most arcs entering and leaving a single node
+    pub max_degree: usize,
+}
+
+/// how many times one side of a node may exceed the other before `capacity_balance_warnings` reports it
+const IMBALANCE_RATIO: u64 = 4;
+
+impl GraphNetwork<KarzanovNode, KarzanovArc> {
+    /// the arcs carrying a positive flow and the nodes they touch, as a new network,
+    /// with the map from the old node ids to the new ones
+    pub fn flow_carrying_subgraph(
+        &self,
+    ) -> (
+        GraphNetwork<KarzanovNode, KarzanovArc>,
+        HashMap<NodeId, NodeId>,
+    ) {
+        let carrying: Vec<(NodeId, NodeId, &KarzanovArc)> = (0..self.arc_data.len())
+            .filter_map(|arc_id| {
+                let (from, into) = self.arc_endpoints(arc_id)?;
+                let arc = self.data_of_arc(arc_id)?;
+                (arc.flow > 0).then_some((from, into, arc))
+            })
+            .collect();
+        let mut used = vec![false; self.node_data.len()];
+        for (from, into, _) in &carrying {
+            used[*from] = true;
+            used[*into] = true;
+        }
+
+        let mut subgraph = GraphNetwork::new();
+        let mut old_new_map = HashMap::new();
+        for (node_id, node) in self.node_data.iter().enumerate() {
+            if let (true, Some(node)) = (used[node_id], node) {
+                old_new_map.insert(node_id, subgraph.add_node(node.clone()));
+            }
+        }
+        for (from, into, arc) in carrying {
+            subgraph.connect(old_new_map[&from], old_new_map[&into], arc.clone());
+        }
+        (subgraph, old_new_map)
+    }
+
+    /// a network with a node per row of a square matrix of capacities,
+    /// and an arc `i -> j` for every nonzero entry `caps[i][j]`. panics unless the matrix is square
+    pub fn from_matrix(caps: &[Vec<u32>]) -> Self {
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); caps.len()].into_iter());
+        for (i, row) in caps.iter().enumerate() {
+            assert_eq!(
+                row.len(),
+                caps.len(),
+                "row {} of the capacity matrix has {} entries instead of {}",
+                i,
+                row.len(),
+                caps.len()
+            );
+            for (j, capacity) in row.iter().enumerate() {
+                if *capacity > 0 {
+                    network.connect(i, j, KarzanovArc::new(*capacity));
+                }
+            }
+        }
+        network
+    }
+
+    /// the residual capacity of every arc around a node as `(neighbor, residual)`:
+    /// `capacity - flow` towards the head of an outgoing arc, then `flow` towards the tail of an incoming arc
+    pub fn residual_out_arcs(&self, node_id: NodeId) -> impl Iterator<Item = (NodeId, i64)> + '_ {
+        let forward = self.from_node(node_id).map(move |(next_id, arc_id)| {
+            let arc = self.data_of_arc(arc_id).unwrap();
+            (next_id, arc.capacity as i64 - arc.flow as i64)
+        });
+        let backward = self
+            .into_node(node_id)
+            .map(move |(prev_id, arc_id)| (prev_id, self.data_of_arc(arc_id).unwrap().flow as i64));
+        forward.chain(backward)
+    }
+
+    /// the two ways flow can move between an ordered pair through its arcs `from -> into`:
+    /// the residual capacity left to push `from -> into`, and the flow that can be cancelled
+    /// to push `into -> from`. parallel arcs are summed up, saturating at `u32::MAX`,
+    /// and an arc carrying more than its capacity leaves no residual
+    pub fn residual_between(&self, from: NodeId, into: NodeId) -> (u32, u32) {
+        self.between_nodes(from, into)
+            .map(|arc_id| self.data_of_arc(arc_id).unwrap())
+            .fold((0u32, 0u32), |(forward, backward), arc| {
+                (
+                    forward.saturating_add(arc.capacity.saturating_sub(arc.flow)),
+                    backward.saturating_add(arc.flow),
+                )
+            })
+    }
+
+    /// add the flow of every arc of `other` to the same arc of `self`, e.g. to sum up sub-flows.
+    /// the result must respect the capacities and be a flow from a single source to a single sink,
+    /// otherwise `self` is left untouched
+    pub fn add_flow_from(
+        &mut self,
+        other: &GraphNetwork<KarzanovNode, KarzanovArc>,
+    ) -> Result<(), FlowError> {
+        let arc_count = self.arc_data.len().max(other.arc_data.len());
+        let mut sums: Vec<(ArcId, NodeId, NodeId, u32)> = Vec::new();
+        for arc_id in 0..arc_count {
+            let (from, into) = match (self.arc_endpoints(arc_id), other.arc_endpoints(arc_id)) {
+                (None, None) => continue,
+                (Some(pair), Some(other_pair)) if pair == other_pair => pair,
+                _ => return Err(FlowError::ArcMismatch(arc_id)),
+            };
+            let arc = self.data_of_arc(arc_id).unwrap();
+            let other_arc = other.data_of_arc(arc_id).unwrap();
+            match arc.flow.checked_add(other_arc.flow) {
+                Some(sum) if sum <= arc.capacity => sums.push((arc_id, from, into, sum)),
+                _ => return Err(FlowError::CapacityExceeded(arc_id)),
+            }
+        }
+
+        // the net outgoing flux of every node
+        let mut net = vec![0i64; self.node_data.len()];
+        for (_, from, into, sum) in &sums {
+            net[*from] += *sum as i64;
+            net[*into] -= *sum as i64;
+        }
+        let mut sources = net.iter().enumerate().filter(|(_, net)| **net > 0);
+        let mut sinks = net.iter().enumerate().filter(|(_, net)| **net < 0);
+        if let Some((node_id, _)) = sources.nth(1).or_else(|| sinks.nth(1)) {
+            return Err(FlowError::Unbalanced(node_id));
+        }
+
+        for (arc_id, _, _, sum) in sums {
+            self.mut_data_of_arc(arc_id).unwrap().flow = sum;
+        }
+        Ok(())
+    }
+    /// the pushes recorded at a node as `(arc, delta)`, the latest last.
+    /// `balance_incoming` pops them to return the excess of the node
+    pub fn node_stack(&self, node_id: NodeId) -> Option<&[(ArcId, u32)]> {
+        self.data_of_node(node_id).map(|node| node.stack.as_slice())
+    }
+
+    /// the least flow between consecutive nodes of `path`, summed over parallel arcs,
+    /// i.e. how much of a solved flow may follow this route. 0 if a pair is not connected, even if a node is missing
+    pub fn flow_along(&self, path: &[NodeId]) -> u32 {
+        path.windows(2)
+            .map(|pair| flow_on(self, pair[0], pair[1]))
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// the flow of every connected ordered pair of nodes, summed over parallel arcs
+    pub fn flow_map(&self) -> HashMap<(NodeId, NodeId), u32> {
+        let mut flows = HashMap::new();
+        for (arc_id, arc) in self.arc_data.iter().enumerate() {
+            if let (Some(arc), Some(pair)) = (arc, self.arc_endpoints(arc_id)) {
+                *flows.entry(pair).or_insert(0) += arc.flow;
+            }
+        }
+        flows
+    }
+
+    /// the outgoing arcs of a node sorted by capacity, keeping the arc order among equal capacities.
+    /// empty for a removed node
+    pub fn out_arcs_by_capacity(&self, node_id: NodeId, descending: bool) -> Vec<(NodeId, ArcId)> {
+        if !self.is_node_in(node_id) {
+            return Vec::new();
+        }
+        let mut arcs: Vec<(NodeId, ArcId)> = self.from_node(node_id).collect();
+        arcs.sort_by(|(_, a), (_, b)| {
+            let (a, b) = (self.data_of_arc(*a).unwrap(), self.data_of_arc(*b).unwrap());
+            if descending {
+                b.capacity.cmp(&a.capacity)
+            } else {
+                a.capacity.cmp(&b.capacity)
+            }
+        });
+        arcs
+    }
+
+    /// copy every node once per time step, the copy of node `v` at step `t` getting the id `t * n + v`
+    /// where `n` is the number of node ids. an arc leaving at step `t` arrives at step `t + transit(arc)`,
+    /// and is dropped if that is beyond the last step. waiting at a node is not modelled
+    pub fn time_expand(
+        &self,
+        time_steps: usize,
+        transit: impl Fn(ArcId) -> usize,
+    ) -> GraphNetwork<KarzanovNode, KarzanovArc> {
+        let node_count = self.node_data.len();
+        let mut expanded = GraphNetwork::new();
+        // every copy goes in before the holes are made, as `add_node` would take a removed id back
+        expanded.add_nodes((0..time_steps * node_count).map(|_| KarzanovNode::new()));
+        for copy_id in 0..time_steps * node_count {
+            if !self.is_node_in(copy_id % node_count) {
+                // keep the id arithmetic by leaving a hole
+                expanded.remove_node(copy_id);
+            }
+        }
+        for (arc_id, arc) in self.arc_data.iter().enumerate() {
+            let (Some(arc), Some((from, into))) = (arc, self.arc_endpoints(arc_id)) else {
+                continue;
+            };
+            let transit = transit(arc_id);
+            for step in 0..time_steps.saturating_sub(transit) {
+                expanded.connect(
+                    step * node_count + from,
+                    (step + transit) * node_count + into,
+                    KarzanovArc::new(arc.capacity),
+                );
+            }
+        }
+        expanded
+    }
+
+    /// order the nodes of a layer so that every arc inside it goes forward.
+    /// on a cycle, return a node on it or behind it
+    fn order_inside_layer(
+        &self,
+        layer: &[NodeId],
+        layer_of: &[Option<usize>],
+    ) -> Result<Vec<NodeId>, NodeId> {
+        let inner_arcs = |node_id: NodeId| {
+            self.from_node(node_id)
+                .filter(move |(next_id, _)| layer_of[*next_id] == layer_of[node_id])
+        };
+        let mut in_degree: HashMap<NodeId, usize> = layer.iter().map(|n| (*n, 0)).collect();
+        for node_id in layer {
+            for (next_id, _) in inner_arcs(*node_id) {
+                *in_degree.get_mut(&next_id).unwrap() += 1;
+            }
+        }
+        let mut ready: Vec<NodeId> = layer
+            .iter()
+            .rev()
+            .filter(|n| in_degree[*n] == 0)
+            .copied()
+            .collect();
+        let mut ordered = Vec::with_capacity(layer.len());
+        while let Some(node_id) = ready.pop() {
+            ordered.push(node_id);
+            for (next_id, _) in inner_arcs(node_id) {
+                let degree = in_degree.get_mut(&next_id).unwrap();
+                *degree -= 1;
+                if *degree == 0 {
+                    ready.push(next_id);
+                }
+            }
+        }
+        match layer.iter().find(|n| in_degree[*n] > 0) {
+            Some(node_id) => Err(*node_id),
+            None => Ok(ordered),
+        }
+    }
+
+    /// the breadth-first layers of the nodes reachable from the source, with the layer of every node
+    fn breadth_first_layers(&self, source_id: NodeId) -> (Vec<Vec<NodeId>>, Vec<Option<usize>>) {
+        let mut layer_of: Vec<Option<usize>> = vec![None; self.node_data.len()];
+        layer_of[source_id] = Some(0);
+        let mut layers: Vec<Vec<NodeId>> = vec![vec![source_id]];
+        loop {
+            let mut next_layer: Vec<NodeId> = Vec::new();
+            for node_id in layers.last().unwrap() {
+                for (next_id, _) in self.from_node(*node_id) {
+                    if self.is_node_in(next_id) && layer_of[next_id].is_none() {
+                        layer_of[next_id] = Some(layers.len());
+                        next_layer.push(next_id);
+                    }
+                }
+            }
+            if next_layer.is_empty() {
+                break;
+            }
+            layers.push(next_layer);
+        }
+        (layers, layer_of)
+    }
+
+    /// the number of nodes in each breadth-first layer from the source up to the layer of the sink,
+    /// or nothing if the sink is unreachable. wide layers are the costly ones to order
+    pub fn layer_widths(&self, source_id: NodeId, sink_id: NodeId) -> Vec<usize> {
+        if !self.is_node_in(source_id) || !self.is_node_in(sink_id) {
+            return Vec::new();
+        }
+        let (layers, layer_of) = self.breadth_first_layers(source_id);
+        match layer_of[sink_id] {
+            Some(d) => layers[..=d].iter().map(|layer| layer.len()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// the width of the widest layer given by `layer_widths`
+    pub fn max_layer_width(&self, source_id: NodeId, sink_id: NodeId) -> usize {
+        self.layer_widths(source_id, sink_id)
+            .into_iter()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// split the nodes into the layers the solver starts from, as `layers` gives them with every arc
+    /// inside a layer going forward where no cycle forbids it, or explain why the source and the sink
+    /// cannot be solved: a missing node or an unreachable sink
+    pub fn explain_layering(
+        &self,
+        source_id: NodeId,
+        sink_id: NodeId,
+    ) -> Result<Vec<Vec<NodeId>>, String> {
+        let mut layers = layers(source_id, sink_id, self).map_err(|error| match error {
+            MaxFlowError::NodeMissing(node_id) => {
+                let role = if node_id == source_id {
+                    "source"
+                } else {
+                    "sink"
+                };
+                format!("the {} {} does not exist", role, node_id)
+            }
+            _ => format!(
+                "the sink {} is not reachable from the source {}",
+                sink_id, source_id
+            ),
+        })?;
+        let mut layer_of = vec![None; self.node_data.len()];
+        for (d, layer) in layers.iter().enumerate() {
+            for node_id in layer {
+                layer_of[*node_id] = Some(d);
+            }
+        }
+        for layer in layers.iter_mut() {
+            // a cycle inside a layer takes no part in the level graph, so the order is left as it is
+            if let Ok(ordered) = self.order_inside_layer(layer, &layer_of) {
+                *layer = ordered;
+            }
+        }
+        Ok(layers)
+    }
+
+    /// lock every saturated arc of a solved network, so that the next solves keep its flow
+    /// as committed and never reroute it. the flow of the other arcs is solved anew around them
+    pub fn lock_saturated_arcs(&mut self) {
+        for arc in self.arc_data.iter_mut().flatten() {
+            if arc.capacity > 0 && arc.flow == arc.capacity {
+                arc.locked = true;
+                arc.open = false;
+            }
+        }
+    }
+
+    /// the total capacities into and out of a node
+    fn capacity_in_out(&self, node_id: NodeId) -> (u64, u64) {
+        let in_cap = self
+            .into_node(node_id)
+            .map(|(_, arc_id)| self.data_of_arc(arc_id).unwrap().capacity as u64)
+            .sum();
+        let out_cap = self
+            .from_node(node_id)
+            .map(|(_, arc_id)| self.data_of_arc(arc_id).unwrap().capacity as u64)
+            .sum();
+        (in_cap, out_cap)
+    }
+
+    /// the most flow each node could ever pass, i.e. the lesser of its total incoming and
+    /// outgoing capacities, by node id. removed nodes get 0, and so does a node without incoming
+    /// or outgoing arcs, such as the usual source and sink. a source with an incoming arc is no exception
+    pub fn inferred_node_throughput_caps(&self) -> Vec<u64> {
+        (0..self.node_data.len())
+            .map(|node_id| {
+                if !self.is_node_in(node_id) {
+                    return 0;
+                }
+                let (in_cap, out_cap) = self.capacity_in_out(node_id);
+                in_cap.min(out_cap)
+            })
+            .collect()
+    }
+
+    /// the intermediate nodes whose total incoming capacity is more than `IMBALANCE_RATIO` times
+    /// the outgoing one or the other way around, as `(node, in_cap, out_cap)`.
+    /// such a node usually comes from a modeling mistake, though the network stays solvable
+    pub fn capacity_balance_warnings(
+        &self,
+        source_id: NodeId,
+        sink_id: NodeId,
+    ) -> Vec<(NodeId, u64, u64)> {
+        let mut warnings = Vec::new();
+        for node_id in 0..self.node_data.len() {
+            if node_id == source_id || node_id == sink_id || !self.is_node_in(node_id) {
+                continue;
+            }
+            let (in_cap, out_cap) = self.capacity_in_out(node_id);
+            if in_cap > IMBALANCE_RATIO * out_cap || out_cap > IMBALANCE_RATIO * in_cap {
+                warnings.push((node_id, in_cap, out_cap));
+            }
+        }
+        warnings
+    }
+
+    /// solve a fresh copy of the network `runs` times, and return the flow value
+    /// with the total time spent, copies included. a micro-benchmark needing no harness
+    pub fn solve_repeatedly(
+        &self,
+        source_id: NodeId,
+        sink_id: NodeId,
+        runs: usize,
+    ) -> Result<(u32, Duration), MaxFlowError> {
+        let mut value = 0;
+        let start = Instant::now();
+        for _ in 0..runs {
+            let mut network = self.clone();
+            value = maxflow(source_id, sink_id, &mut network)?;
+        }
+        Ok((value, start.elapsed()))
+    }
+    /// add a super-sink fed by every node without outgoing arcs, and return its id.
+    /// each new arc takes the total capacity into its tail, so it never limits the flow
+    pub fn auto_single_sink(&mut self) -> NodeId {
+        let sinks: Vec<(NodeId, u32)> = (0..self.node_data.len())
+            .filter(|node_id| {
+                self.is_node_in(*node_id) && self.from_node(*node_id).next().is_none()
+            })
+            .map(|node_id| {
+                let capacity = self
+                    .into_node(node_id)
+                    .map(|(_, arc_id)| self.data_of_arc(arc_id).unwrap().capacity)
+                    .fold(0, u32::saturating_add);
+                (node_id, capacity)
+            })
+            .collect();
+        let super_sink_id = self.add_node(KarzanovNode::new());
+        for (node_id, capacity) in sinks {
+            self.connect(node_id, super_sink_id, KarzanovArc::new(capacity));
+        }
+        super_sink_id
+    }
+
+    /// add a super-source feeding every node without incoming arcs, and return its id.
+    /// each new arc takes the total capacity out of its head, so it never limits the flow
+    pub fn auto_single_source(&mut self) -> NodeId {
+        let sources: Vec<(NodeId, u32)> = (0..self.node_data.len())
+            .filter(|node_id| {
+                self.is_node_in(*node_id) && self.into_node(*node_id).next().is_none()
+            })
+            .map(|node_id| {
+                let capacity = self
+                    .from_node(node_id)
+                    .map(|(_, arc_id)| self.data_of_arc(arc_id).unwrap().capacity)
+                    .fold(0, u32::saturating_add);
+                (node_id, capacity)
+            })
+            .collect();
+        let super_source_id = self.add_node(KarzanovNode::new());
+        for (node_id, capacity) in sources {
+            self.connect(super_source_id, node_id, KarzanovArc::new(capacity));
+        }
+        super_source_id
+    }
+    /// disconnect an arc of a solved network and solve it again, returning the new flow value.
+    /// the flow the arc carried is taken back along the paths of `decompose_flow` through it,
+    /// and along the cycles through it for the rest, and the solve goes on from the flow left
+    pub fn disconnect_and_rebalance(
+        &mut self,
+        arc_id: ArcId,
+        source_id: NodeId,
+        sink_id: NodeId,
+    ) -> Result<u32, MaxFlowError> {
+        for node_id in [source_id, sink_id] {
+            if !self.is_node_in(node_id) {
+                return Err(MaxFlowError::NodeMissing(node_id));
+            }
+        }
+        for (path, amount) in decompose_flow(source_id, sink_id, self) {
+            if path.contains(&arc_id) {
+                for id in path {
+                    self.mut_data_of_arc(id).unwrap().flow -= amount;
+                }
+            }
+        }
+        while let Some(cycle) = self.flow_cycle_through(arc_id) {
+            let amount = cycle
+                .iter()
+                .map(|id| self.data_of_arc(*id).unwrap().flow)
+                .min()
+                .unwrap();
+            for id in cycle {
+                self.mut_data_of_arc(id).unwrap().flow -= amount;
+            }
+        }
+        self.disconnect(arc_id);
+        maxflow_warmstart(source_id, sink_id, self)
+    }
+
+    /// a cycle of arcs carrying flow which starts with the arc, if it still carries some
+    fn flow_cycle_through(&self, arc_id: ArcId) -> Option<Vec<ArcId>> {
+        let (from, into) = self.arc_endpoints(arc_id)?;
+        if self.data_of_arc(arc_id)?.flow == 0 {
+            return None;
+        }
+        // depth-first search from the head back to the tail along the arcs with some flow
+        let mut reached_by: Vec<Option<ArcId>> = vec![None; self.node_data.len()];
+        let mut visited = vec![false; self.node_data.len()];
+        visited[into] = true;
+        let mut stack = vec![into];
+        while let Some(node_id) = stack.pop() {
+            if node_id == from {
+                break;
+            }
+            for (next_id, next_arc_id) in self.from_node(node_id) {
+                if !visited[next_id] && self.data_of_arc(next_arc_id).unwrap().flow > 0 {
+                    visited[next_id] = true;
+                    reached_by[next_id] = Some(next_arc_id);
+                    stack.push(next_id);
                 }
             }
         }
+        let mut cycle = Vec::new();
+        let mut node_id = from;
+        while node_id != into {
+            let back_arc_id = reached_by[node_id]?;
+            cycle.push(back_arc_id);
+            node_id = self.arc_endpoints(back_arc_id).unwrap().0;
+        }
+        cycle.push(arc_id);
+        cycle.reverse();
+        Some(cycle)
+    }
+    /// join two nodes by an undirected edge of `capacity`, as a pair of arcs `a -> b` and `b -> a`
+    /// that offset each other: once solved, at most one of them carries flow, the net flow between the nodes
+    pub fn connect_undirected(&mut self, a: NodeId, b: NodeId, capacity: u32) -> (ArcId, ArcId) {
+        let forward_id = self.connect(a, b, KarzanovArc::new(capacity));
+        let backward_id = self.connect(b, a, KarzanovArc::new(capacity));
+        self.mut_data_of_arc(forward_id).unwrap().twin = Some(backward_id);
+        self.mut_data_of_arc(backward_id).unwrap().twin = Some(forward_id);
+        (forward_id, backward_id)
+    }
+    /// count the live nodes and arcs in a single pass over each
+    pub fn stats(&self) -> GraphStats {
+        let nodes = self.node_count();
+        let mut degrees = vec![0; self.node_data.len()];
+        let mut arcs = 0;
+        let mut total_capacity = 0;
+        for (arc_id, arc) in self.arc_data.iter().enumerate() {
+            let (Some(arc), Some((from, into))) = (arc, self.arc_endpoints(arc_id)) else {
+                continue;
+            };
+            arcs += 1;
+            total_capacity += arc.capacity as u64;
+            degrees[from] += 1;
+            degrees[into] += 1;
+        }
+        GraphStats {
+            nodes,
+            arcs,
+            total_capacity,
+            max_degree: degrees.into_iter().max().unwrap_or(0),
+        }
+    }
+
+    /// collapse every chain of nodes with exactly one incoming and one outgoing arc into a single arc
+    /// of the least capacity along it, carrying the flow of its first arc. return the nodes each new arc
+    /// replaced, in order, so that a flow can be expanded back onto them. a chain closing into a cycle stays
+    pub fn compress_chains(&mut self) -> HashMap<ArcId, Vec<NodeId>> {
+        let is_link = |network: &Self, node_id: NodeId| {
+            network.is_node_in(node_id)
+                && network.into_node(node_id).count() == 1
+                && network.from_node(node_id).count() == 1
+        };
+        let mut replaced = HashMap::new();
+        for node_id in 0..self.node_data.len() {
+            if !is_link(self, node_id) {
+                continue;
+            }
+            let (head_id, first_arc_id) = self.into_node(node_id).next().unwrap();
+            if is_link(self, head_id) {
+                // not the start of the chain
+                continue;
+            }
+            let mut arc_ids = vec![first_arc_id];
+            let mut links = Vec::new();
+            let mut tail_id = node_id;
+            while is_link(self, tail_id) && tail_id != head_id {
+                links.push(tail_id);
+                let (next_id, arc_id) = self.from_node(tail_id).next().unwrap();
+                arc_ids.push(arc_id);
+                tail_id = next_id;
+            }
+            if tail_id == head_id {
+                continue;
+            }
+            let capacity = arc_ids
+                .iter()
+                .map(|arc_id| self.data_of_arc(*arc_id).unwrap().capacity)
+                .min()
+                .unwrap();
+            let mut arc = KarzanovArc::new(capacity);
+            arc.flow = min(self.data_of_arc(first_arc_id).unwrap().flow, capacity);
+            for arc_id in arc_ids {
+                self.disconnect(arc_id);
+            }
+            for link_id in &links {
+                self.remove_node(*link_id);
+            }
+            replaced.insert(self.connect(head_id, tail_id, arc), links);
+        }
+        replaced
+    }
+
+    /// split every arc running against another one, from the larger node id to the smaller, into two arcs
+    /// through a new node, so that no two arcs are antiparallel. return the two arcs each split arc became,
+    /// either of which carries its flow once solved. the other arcs keep their ids
+    pub fn split_antiparallel(&mut self) -> HashMap<ArcId, [ArcId; 2]> {
+        let mut halves = HashMap::new();
+        for arc_id in 0..self.arc_data.len() {
+            let Some((from, into)) = self.arc_endpoints(arc_id) else {
+                continue;
+            };
+            if from <= into || self.between_nodes(into, from).next().is_none() {
+                continue;
+            }
+            let arc = self.disconnect(arc_id).unwrap();
+            let capacity = arc.capacity;
+            let middle_id = self.add_node(KarzanovNode::new());
+            let first_id = self.connect(from, middle_id, arc);
+            let second_id = self.connect(middle_id, into, KarzanovArc::new(capacity));
+            halves.insert(arc_id, [first_id, second_id]);
+        }
+        halves
+    }
+}
+
+/// the residual network of a network with a flow, under the same node ids: a forward arc of `capacity - flow`
+/// and a backward arc of `flow`, running `into -> from`, take the place of each arc, either left out if it would be empty.
+/// locked arcs and self-loops take no part, as no flow can be rerouted over them
+pub fn residual_graph(
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> GraphNetwork<KarzanovNode, KarzanovArc> {
+    let (mut residual, _) = residual_with_origins(network);
+    for node_id in 0..network.node_data.len() {
+        if !network.is_node_in(node_id) {
+            residual.remove_node(node_id);
+        }
+    }
+    residual
+}
+
+/// whether each node is reachable from the source through arcs of positive residual capacity
+fn residual_reachable(
+    source_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Vec<bool> {
+    let mut reached = vec![false; network.node_data.len()];
+    reached[source_id] = true;
+    let mut stack = vec![source_id];
+    while let Some(node_id) = stack.pop() {
+        for (next_id, residual) in network.residual_out_arcs(node_id) {
+            if !reached[next_id] && residual > 0 {
+                reached[next_id] = true;
+                stack.push(next_id);
+            }
+        }
+    }
+    reached
+}
+
+/// the flow from one node into another, summed over parallel arcs, e.g. `flow_on(&network, 0, 1)`.
+/// 0 if no arc joins them, even if a node is missing
+pub fn flow_on(
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+    from_node_id: NodeId,
+    into_node_id: NodeId,
+) -> u32 {
+    // `between_nodes` panics on a missing node
+    if !network.is_node_in(from_node_id) || !network.is_node_in(into_node_id) {
+        return 0;
+    }
+    network
+        .between_nodes(from_node_id, into_node_id)
+        .map(|arc_id| network.data_of_arc(arc_id).unwrap().flow)
+        .sum()
+}
+
+/// split the nodes of a solved network by the reachability from the source in the residual network.
+/// return the source side, the sink side and the arcs crossing from the former to the latter,
+/// whose capacities sum up to the flow value
+pub fn min_cut(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> (Vec<NodeId>, Vec<NodeId>, Vec<ArcId>) {
+    let reached = residual_reachable(source_id, network);
+    debug_assert!(!reached[sink_id], "the flow is not maximum");
+
+    let (mut source_side, mut sink_side) = (Vec::new(), Vec::new());
+    for (node_id, reached) in reached.iter().enumerate() {
+        if !network.is_node_in(node_id) {
+            continue;
+        }
+        if *reached {
+            source_side.push(node_id);
+        } else {
+            sink_side.push(node_id);
+        }
+    }
+    let mut cut_arcs = Vec::new();
+    for node_id in &source_side {
+        for (next_id, arc_id) in network.from_node(*node_id) {
+            if !reached[next_id] {
+                cut_arcs.push(arc_id);
+            }
+        }
+    }
+    (source_side, sink_side, cut_arcs)
+}
+
+/// split the flow of a solved network into source-to-sink paths of arcs with their amounts.
+/// every path takes the bottleneck of the remaining flow along it, so the amounts sum up
+/// to the flow value and the paths through an arc sum up to its flow, but for flow running in cycles
+pub fn decompose_flow(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Vec<(Vec<ArcId>, u32)> {
+    let mut remaining: Vec<u32> = network
+        .arc_data
+        .iter()
+        .map(|arc| arc.as_ref().map_or(0, |arc| arc.flow))
+        .collect();
+    let mut paths = Vec::new();
+    loop {
+        // depth-first search along the arcs with some flow left
+        let mut visited = vec![false; network.node_data.len()];
+        visited[source_id] = true;
+        let mut path: Vec<ArcId> = Vec::new();
+        let mut node_id = source_id;
+        while node_id != sink_id {
+            let next = network
+                .from_node(node_id)
+                .find(|(next_id, arc_id)| remaining[*arc_id] > 0 && !visited[*next_id]);
+            match next {
+                Some((next_id, arc_id)) => {
+                    visited[next_id] = true;
+                    path.push(arc_id);
+                    node_id = next_id;
+                }
+                None => match path.pop() {
+                    // a dead end, so step back
+                    Some(arc_id) => node_id = network.arc_endpoints(arc_id).unwrap().0,
+                    None => return paths,
+                },
+            }
+        }
+        let amount = path.iter().map(|arc_id| remaining[*arc_id]).min().unwrap();
+        for arc_id in &path {
+            remaining[*arc_id] -= amount;
+        }
+        paths.push((path, amount));
+    }
+}
+
+/// solve the network and count the arcs of its minimum cut.
+/// it tells how many simultaneous arc failures may reduce the flow
+pub fn min_cut_edge_multiplicity(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Result<usize, MaxFlowError> {
+    maxflow(source_id, sink_id, network)?;
+    let (_, _, cut_arcs) = min_cut(source_id, sink_id, network);
+    Ok(cut_arcs.len())
+}
+
+/// solve the network as if the arcs thinner than `threshold` were absent, and return the flow value.
+/// the thin arcs end up with no flow, and so does every arc if they cut the sink off
+pub fn solve_above_threshold(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+    threshold: u32,
+) -> Result<u32, MaxFlowError> {
+    let mut fat_network = network.clone();
+    for arc_id in 0..fat_network.arc_data.len() {
+        if fat_network
+            .data_of_arc(arc_id)
+            .is_some_and(|arc| arc.capacity < threshold)
+        {
+            // disconnecting keeps the ids of the other arcs
+            fat_network.disconnect(arc_id);
+        }
+    }
+    clean_network(network);
+    match maxflow(source_id, sink_id, &mut fat_network) {
+        Err(MaxFlowError::SinkUnreachable) => return Ok(0),
+        result => result?,
+    };
+    for (arc, fat_arc) in network.arc_data.iter_mut().zip(fat_network.arc_data) {
+        if let (Some(arc), Some(fat_arc)) = (arc, fat_arc) {
+            *arc = fat_arc;
+        }
+    }
+    Ok(flow_value(source_id, network))
+}
+
+/// the least amount to add to the capacity of every arc so that the flow value reaches `target`.
+/// `None` if no amount helps, as when too few paths lead to the sink, or if a solve fails. the network is solved as it is
+pub fn min_capacity_for_flow(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+    target: u32,
+) -> Option<u32> {
+    // zero-capacity arcs may still lead to the sink once they gain some
+    let flow_value_of = |network: &mut GraphNetwork<KarzanovNode, KarzanovArc>| {
+        maxflow_general(source_id, sink_id, network).ok()
+    };
+    if flow_value_of(network)? >= target {
+        return Some(0);
+    }
+    let flow_value_with = |uplift: u32| {
+        let mut uplifted = network.clone();
+        for arc in uplifted.arc_data.iter_mut().flatten() {
+            arc.capacity = arc.capacity.saturating_add(uplift);
+        }
+        flow_value_of(&mut uplifted)
+    };
+    // any path reaches the target once every arc has gained `target`, so without a path no amount helps
+    if flow_value_with(target)? < target {
+        return None;
+    }
+    let (mut low, mut high) = (0, target);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        if flow_value_with(middle)? >= target {
+            high = middle;
+        } else {
+            low = middle;
+        }
+    }
+    Some(high)
+}
+
+/// panic with the culprit unless the flow of the network respects the capacities,
+/// is balanced at every intermediate node and leaves no augmenting path
+fn assert_optimal(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) {
+    for (arc_id, arc) in network.arc_data.iter().enumerate() {
+        if let Some(arc) = arc {
+            assert!(
+                arc.flow <= arc.capacity,
+                "arc {} carries {} over its capacity {}",
+                arc_id,
+                arc.flow,
+                arc.capacity
+            );
+        }
+    }
+    for node_id in 0..network.node_data.len() {
+        if node_id == source_id || node_id == sink_id || !network.is_node_in(node_id) {
+            continue;
+        }
+        let incoming_flux = incoming_flux_of_flow(node_id, network);
+        let outgoing_flux = outgoing_flux_of_flow(node_id, network);
+        assert!(
+            incoming_flux == outgoing_flux,
+            "node {} takes in {} but sends out {}",
+            node_id,
+            incoming_flux,
+            outgoing_flux
+        );
+    }
+    assert!(
+        !residual_reachable(source_id, network)[sink_id],
+        "an augmenting path from {} to {} remains, so the flow of {} is not maximum",
+        source_id,
+        sink_id,
+        flow_value(source_id, network)
+    );
+}
+
+/// solve the network and return the flow value, panicking with details
+/// if the flow is invalid or not maximum. meant for the tests of the users
+pub fn solve_and_assert_optimal(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &mut GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> u32 {
+    let value = maxflow(source_id, sink_id, network).unwrap_or_else(|error| panic!("{}", error));
+    assert_optimal(source_id, sink_id, network);
+    value
+}
+
+/// a copy of the network with every capacity set to 1, solved, so that its flow takes each arc at most once
+fn solve_unit_copy(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Result<GraphNetwork<KarzanovNode, KarzanovArc>, MaxFlowError> {
+    let mut unit_network = network.clone();
+    for arc in unit_network.arc_data.iter_mut().flatten() {
+        arc.capacity = 1;
+        arc.lower = 0;
+    }
+    maxflow_general(source_id, sink_id, &mut unit_network)?;
+    Ok(unit_network)
+}
+
+/// the maximum number of source-to-sink paths sharing no arc, which is the flow value with unit capacities
+/// by menger's theorem. the network is left as it is. fail if the source or the sink is missing
+pub fn edge_disjoint_paths(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Result<usize, MaxFlowError> {
+    let unit_network = solve_unit_copy(source_id, sink_id, network)?;
+    Ok(flow_value(source_id, &unit_network) as usize)
+}
+
+/// as many source-to-sink paths of arcs sharing no arc as there can be, found by `decompose_flow`
+pub fn edge_disjoint_path_arcs(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Result<Vec<Vec<ArcId>>, MaxFlowError> {
+    let unit_network = solve_unit_copy(source_id, sink_id, network)?;
+    Ok(decompose_flow(source_id, sink_id, &unit_network)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect())
+}
+
+/// a copy of the network in which every node but the source and the sink splits into an in-node,
+/// which keeps its id and its incoming arcs, and a new out-node taking the outgoing arcs, joined by an arc of capacity 1.
+/// return it with the `(in-node, out-node)` of every node, the same one twice for the source and the sink
+pub fn split_nodes(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> (
+    GraphNetwork<KarzanovNode, KarzanovArc>,
+    HashMap<NodeId, (NodeId, NodeId)>,
+) {
+    let mut split = GraphNetwork::new();
+    split.add_nodes((0..network.node_data.len()).map(|_| KarzanovNode::new()));
+    let live: Vec<NodeId> = (0..network.node_data.len())
+        .filter(|node_id| network.is_node_in(*node_id))
+        .collect();
+    for node_id in 0..network.node_data.len() {
+        if !network.is_node_in(node_id) {
+            split.remove_node(node_id);
+        }
+    }
+    // the out-nodes may take the ids of the removed nodes
+    let mut halves = HashMap::new();
+    for node_id in live {
+        let out_id = if node_id == source_id || node_id == sink_id {
+            node_id
+        } else {
+            let out_id = split.add_node(KarzanovNode::new());
+            split.connect(node_id, out_id, KarzanovArc::new(1));
+            out_id
+        };
+        halves.insert(node_id, (node_id, out_id));
+    }
+    for (arc_id, arc) in network.arc_data.iter().enumerate() {
+        let (Some(arc), Some((from, into))) = (arc, network.arc_endpoints(arc_id)) else {
+            continue;
+        };
+        split.connect(
+            halves[&from].1,
+            halves[&into].0,
+            KarzanovArc::new(arc.capacity),
+        );
+    }
+    (split, halves)
+}
+
+/// the maximum number of source-to-sink paths sharing no node but the source and the sink,
+/// which is the number of edge-disjoint paths once `split_nodes` lets every other node carry one path.
+/// the network is left as it is. fail if the source or the sink is missing
+pub fn vertex_disjoint_paths(
+    source_id: NodeId,
+    sink_id: NodeId,
+    network: &GraphNetwork<KarzanovNode, KarzanovArc>,
+) -> Result<usize, MaxFlowError> {
+    // an out-node could take the id of a removed source or sink
+    for node_id in [source_id, sink_id] {
+        if !network.is_node_in(node_id) {
+            return Err(MaxFlowError::NodeMissing(node_id));
+        }
+    }
+    let (split, _) = split_nodes(source_id, sink_id, network);
+    edge_disjoint_paths(source_id, sink_id, &split)
+}
+
+/// a maximum matching of a bipartite graph with `left` and `right` nodes, each numbered from 0,
+/// as the pairs `(left node, right node)` of `edges` it uses, panicking on a node out of range. it solves the unit-capacity network
+/// source -> left -> right -> sink, whose saturated middle arcs are the matched pairs.
+/// fail only as the solver does on its own bugs
+pub fn bipartite_matching(
+    left: usize,
+    right: usize,
+    edges: &[(usize, usize)],
+) -> Result<Vec<(usize, usize)>, MaxFlowError> {
+    let mut network = GraphNetwork::new();
+    network.add_nodes((0..left + right + 2).map(|_| KarzanovNode::new()));
+    let (source_id, sink_id) = (0, left + right + 1);
+    for node_id in 1..=left {
+        network.connect(source_id, node_id, KarzanovArc::new(1));
+    }
+    for node_id in left + 1..=left + right {
+        network.connect(node_id, sink_id, KarzanovArc::new(1));
+    }
+    let middle_arcs: Vec<ArcId> = edges
+        .iter()
+        .map(|(l, r)| {
+            assert!(
+                *l < left && *r < right,
+                "edge ({}, {}) is out of range",
+                l,
+                r
+            );
+            network.connect(1 + l, 1 + left + r, KarzanovArc::new(1))
+        })
+        .collect();
+    // without any edge the sink is out of reach, which is an empty matching
+    maxflow_general(source_id, sink_id, &mut network)?;
+    Ok(edges
+        .iter()
+        .zip(middle_arcs)
+        .filter(|(_, arc_id)| network.data_of_arc(*arc_id).unwrap().flow == 1)
+        .map(|(edge, _)| *edge)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::instances::*;
+
+    /// source, sink, network
+    pub fn make_network_instance() -> (NodeId, NodeId, GraphNetwork<KarzanovNode, KarzanovArc>) {
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 9].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(1)),
+                (0, 3, KarzanovArc::new(8)),
+                (1, 2, KarzanovArc::new(2)),
+                (1, 4, KarzanovArc::new(1)),
+                (2, 5, KarzanovArc::new(1)),
+                (3, 1, KarzanovArc::new(4)),
+                (3, 4, KarzanovArc::new(2)),
+                (3, 6, KarzanovArc::new(4)),
+                (4, 5, KarzanovArc::new(3)),
+                (5, 8, KarzanovArc::new(4)),
+                (6, 7, KarzanovArc::new(2)),
+                (6, 5, KarzanovArc::new(1)),
+                (7, 8, KarzanovArc::new(2)),
+            ]
+            .into_iter(),
+        );
+        (0, 8, network)
+    }
+
+    #[test]
+    fn karzanov() {
+        let (source_id, sink_id, mut network) = make_network_instance();
+        let layers = grouping_nodes_by_layer(source_id, sink_id, &mut network).unwrap();
+        println!("Network: {:?}", network);
+        println!("Layers: {:?}", layers);
+        let mut start_layer = 0;
+        let mut flow_snapshot = HashMap::<NodeId, u32>::new();
+
+        loop {
+            println!("===compleation===");
+            maximize_outgoing(
+                &layers,
+                start_layer,
+                &mut network,
+                None,
+                false,
+                &mut MaxFlowStats::default(),
+            )
+            .unwrap();
+            println!("Network: {:?}", network);
+            println!("===balancing===");
+            let new_start_layer =
+                balance_incoming(&layers, &mut network, &mut MaxFlowStats::default()).unwrap();
+            println!("Network: {:?}", network);
+
+            if new_start_layer.is_none() {
+                println!("nothing to be balanced");
+                break;
+            }
+            start_layer = new_start_layer.unwrap();
+
+            // compare with the snapshot
+            let mut different = false;
+            for (arc_id, arc) in network.arc_data.iter().enumerate() {
+                if let Some(arc) = arc {
+                    if flow_snapshot
+                        .get(&arc_id)
+                        .is_none_or(|snapshot| &arc.flow != snapshot)
+                    {
+                        println!("{:?} != {:?}", flow_snapshot.get(&arc_id), &arc.flow);
+                        different = true;
+                        break;
+                    }
+                    println!("{:?} == {:?}", flow_snapshot.get(&arc_id), &arc.flow)
+                }
+            }
+            if !different {
+                println!("no change");
+                break;
+            }
+            // take a snapshot of the flow
+            for (arc_id, arc) in network.arc_data.iter().enumerate() {
+                if let Some(arc) = arc {
+                    flow_snapshot.insert(arc_id, arc.flow);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn min_cut_edges() {
+        // both {0} and {0, 1, 2, 3, 4} are cuts of two arcs with the total capacity of 5.
+        // the residual search stops right at the saturated arcs of the source
+        let (source_id, sink_id, mut network) = network_instance1();
+        assert_eq!(
+            min_cut_edge_multiplicity(source_id, sink_id, &mut network).unwrap(),
+            2
+        );
+        let (source_side, sink_side, cut_arcs) = super::min_cut(source_id, sink_id, &network);
+        assert_eq!(source_side, vec![0]);
+        assert_eq!(sink_side, vec![1, 2, 3, 4, 5]);
+        assert_eq!(cut_arcs, vec![0, 1]);
+    }
+
+    #[test]
+    fn zero_capacity_arcs() {
+        let (source_id, sink_id, mut network) = network_instance1();
+        // parallel, intra-layer and layer-crossing arcs which can carry nothing
+        let zero_arcs: Vec<ArcId> = [(0, 1), (1, 2), (1, 4), (2, 3), (4, 5)]
+            .into_iter()
+            .map(|(from, into)| network.connect(from, into, KarzanovArc::new(0)))
+            .collect();
+        maxflow(source_id, sink_id, &mut network).unwrap();
+
+        assert_eq!(flow_value(source_id, &network), 5);
+        assert_eq!(incoming_flux_of_flow(sink_id, &network), 5);
+        for arc_id in &zero_arcs {
+            assert_eq!(network.data_of_arc(*arc_id).unwrap().flow, 0);
+        }
+        for node in network.node_data.iter().flatten() {
+            assert!(node
+                .stack
+                .iter()
+                .all(|(arc_id, _)| !zero_arcs.contains(arc_id)));
+        }
+    }
+
+    #[test]
+    fn stop_at_upper_bound() {
+        // the sink can take only 2, while the first push floods the dead end 2
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 5].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(5)),
+                (1, 2, KarzanovArc::new(5)),
+                (1, 3, KarzanovArc::new(5)),
+                (2, 4, KarzanovArc::new(1)),
+                (3, 4, KarzanovArc::new(1)),
+            ]
+            .into_iter(),
+        );
+        // the first phase routes 1 through 2, the second one routes 1 through 3 and hits the bound
+        assert_eq!(
+            run_phases(
+                0,
+                4,
+                &mut network,
+                None,
+                false,
+                &mut MaxFlowStats::default()
+            )
+            .unwrap(),
+            2
+        );
+        assert_eq!(flow_value(0, &network), 2);
+        assert_eq!(incoming_flux_of_flow(4, &network), 2);
+
+        // the middle arc keeps the flow below the bound of 5, so there is no early exit
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(5)),
+                (1, 2, KarzanovArc::new(1)),
+                (2, 3, KarzanovArc::new(5)),
+            ]
+            .into_iter(),
+        );
+        run_phases(
+            0,
+            3,
+            &mut network,
+            None,
+            false,
+            &mut MaxFlowStats::default(),
+        )
+        .unwrap();
+        assert_eq!(flow_value(0, &network), 1);
+        assert_eq!(incoming_flux_of_flow(3, &network), 1);
+    }
+
+    #[test]
+    fn maximum_beyond_a_blocking_flow() {
+        // 1 may send its unit on to 3 or to 4. through 3 it fills 3 -> 5, which is the only way on for 2,
+        // so a maximum flow sends it through 4 and the unit of 2 through 3
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 6].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(1)),
+                (0, 2, KarzanovArc::new(2)),
+                (1, 3, KarzanovArc::new(4)),
+                (2, 3, KarzanovArc::new(3)),
+                (1, 4, KarzanovArc::new(2)),
+                (3, 5, KarzanovArc::new(1)),
+                (4, 5, KarzanovArc::new(2)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(maxflow(0, 5, &mut network), Ok(2));
+        assert!(network.all_nodes_balanced(0, 5));
+    }
+
+    #[test]
+    fn flow_map() {
+        let (source_id, sink_id, mut network) = network_instance1();
+        network.connect(2, 3, KarzanovArc::new(1));
+        maxflow(source_id, sink_id, &mut network).unwrap();
+
+        let flows = network.flow_map();
+        assert_eq!(flows.len(), 7);
+        let parallel_flow =
+            network.data_of_arc(3).unwrap().flow + network.data_of_arc(7).unwrap().flow;
+        assert_eq!(flows[&(2, 3)], parallel_flow);
+        assert_eq!(flows[&(0, 1)], 2);
+        assert!(!flows.contains_key(&(3, 2)));
+    }
+
+    #[test]
+    fn out_arcs_by_capacity() {
+        let (source_id, _, mut network) = network_instance1();
+        network.connect(source_id, 2, KarzanovArc::new(2));
+        assert_eq!(
+            network.out_arcs_by_capacity(source_id, true),
+            vec![(2, 1), (1, 0), (2, 7)]
+        );
+        assert_eq!(
+            network.out_arcs_by_capacity(source_id, false),
+            vec![(1, 0), (2, 7), (2, 1)]
+        );
+        network.remove_node(source_id);
+        assert_eq!(network.out_arcs_by_capacity(source_id, true), vec![]);
+    }
+
+    #[test]
+    fn time_expand() {
+        let (_, _, chain) = network_instance4();
+        let mut expanded = chain.time_expand(3, |_| 1);
+        assert_eq!(expanded.node_data.len(), 6);
+        assert_eq!(expanded.arc_data.len(), 2);
+        // (node 0, step 0) -> (node 1, step 1), (node 0, step 1) -> (node 1, step 2)
+        assert_eq!(expanded.arc_endpoints(0), Some((0, 3)));
+        assert_eq!(expanded.arc_endpoints(1), Some((2, 5)));
+
+        maxflow(2, 5, &mut expanded).unwrap();
+        assert_eq!(flow_value(2, &expanded), 1);
+        assert_eq!(expanded.data_of_arc(0).unwrap().flow, 0);
+
+        // nothing arrives within the horizon
+        assert_eq!(chain.time_expand(3, |_| 3).arc_data.len(), 0);
+
+        // the copies of a removed node stay holes at their places
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 3].into_iter());
+        network.connect(0, 2, KarzanovArc::new(1));
+        network.remove_node(1);
+        let expanded = network.time_expand(2, |_| 1);
+        assert_eq!(expanded.node_data.len(), 6);
+        assert!(!expanded.is_node_in(1) && !expanded.is_node_in(4));
+        assert_eq!(expanded.arc_endpoints(0), Some((0, 5)));
+    }
+
+    #[test]
+    fn explain_layering() {
+        let (source_id, sink_id, network) = network_instance2();
+        // 3 -> 1 stays inside the first layer, so 3 comes first
+        assert_eq!(
+            network.explain_layering(source_id, sink_id),
+            Ok(vec![
+                vec![0],
+                vec![3, 1],
+                vec![2, 4, 6],
+                vec![5, 7],
+                vec![8]
+            ])
+        );
+
+        let chain = |arcs: Vec<(NodeId, NodeId)>| {
+            let mut network = GraphNetwork::new();
+            network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+            network.bulk_connect(
+                arcs.into_iter()
+                    .map(|(from, into)| (from, into, KarzanovArc::new(1))),
+            );
+            network
+        };
+        let messages = [
+            chain(vec![(0, 1), (1, 2)]).explain_layering(0, 3),
+            chain(vec![(0, 1), (1, 2), (0, 3)]).explain_layering(0, 3),
+            chain(vec![(0, 1), (1, 2), (2, 3), (2, 1)]).explain_layering(0, 3),
+            chain(vec![(0, 1), (0, 2), (1, 2), (2, 1), (1, 3), (2, 3)]).explain_layering(0, 3),
+            chain(vec![(0, 1)]).explain_layering(0, 9),
+        ];
+        assert_eq!(
+            messages[0],
+            Err("the sink 3 is not reachable from the source 0".to_string())
+        );
+        // nodes past the sink, arcs going back and cycles inside a layer no longer stop a solve
+        assert_eq!(messages[1], Ok(vec![vec![0], vec![3]]));
+        assert_eq!(messages[2], Ok(vec![vec![0], vec![1], vec![2], vec![3]]));
+        assert_eq!(messages[3], Ok(vec![vec![0], vec![1, 2], vec![3]]));
+        assert_eq!(messages[4], Err("the sink 9 does not exist".to_string()));
+    }
+
+    #[test]
+    fn add_flow_from() {
+        let (source_id, sink_id, mut solved) = network_instance1();
+        maxflow(source_id, sink_id, &mut solved).unwrap();
+        let with_flows = |flows: &[(ArcId, u32)]| {
+            let (_, _, mut network) = network_instance1();
+            for (arc_id, flow) in flows {
+                network.mut_data_of_arc(*arc_id).unwrap().flow = *flow;
+            }
+            network
+        };
+        // 0 -> 1 -> 3 -> 5 carrying 2, and the rest of the solved flow
+        let mut half = with_flows(&[(0, 2), (2, 2), (5, 2)]);
+        let other_half = with_flows(&[(1, 3), (3, 1), (4, 2), (5, 1), (6, 2)]);
+
+        assert_eq!(half.add_flow_from(&other_half), Ok(()));
+        assert_eq!(flow_value(source_id, &half), 5);
+        for arc_id in 0..solved.arc_data.len() {
+            assert_eq!(
+                half.data_of_arc(arc_id).unwrap().flow,
+                solved.data_of_arc(arc_id).unwrap().flow
+            );
+        }
+
+        // the failures leave the network as it was
+        assert_eq!(
+            half.add_flow_from(&other_half),
+            Err(FlowError::CapacityExceeded(1))
+        );
+        // 2 -> 3 alone makes 2 a second source
+        let stray = with_flows(&[(3, 1)]);
+        assert_eq!(
+            with_flows(&[(0, 2), (2, 2), (5, 2)]).add_flow_from(&stray),
+            Err(FlowError::Unbalanced(2))
+        );
+        let mut extended = with_flows(&[]);
+        extended.connect(1, 4, KarzanovArc::new(1));
+        assert_eq!(
+            half.add_flow_from(&extended),
+            Err(FlowError::ArcMismatch(7))
+        );
+        assert_eq!(flow_value(source_id, &half), 5);
+    }
+
+    #[test]
+    fn solve_above_threshold() {
+        // only 0 -> 2 -> 3 -> 5 is at least 3 wide
+        let (source_id, sink_id, mut network) = network_instance1();
+        assert_eq!(
+            super::solve_above_threshold(source_id, sink_id, &mut network, 3).unwrap(),
+            3
+        );
+        for arc in network.arc_data.iter().flatten() {
+            if arc.capacity < 3 {
+                assert_eq!(arc.flow, 0);
+            }
+        }
+        assert_eq!(network.data_of_arc(3).unwrap().flow, 3);
+        assert_eq!(
+            super::solve_above_threshold(source_id, sink_id, &mut network, 0).unwrap(),
+            5
+        );
+    }
+
+    #[test]
+    fn residual_out_arcs() {
+        let (source_id, sink_id, mut network) = network_instance1();
+        maxflow(source_id, sink_id, &mut network).unwrap();
+        // both arcs of the source are saturated
+        assert_eq!(
+            network.residual_out_arcs(source_id).collect::<Vec<_>>(),
+            vec![(1, 0), (2, 0)]
+        );
+        // 2 -> 3 carries 1 of 4, 2 -> 4 is saturated, and 0 -> 2 carries 3
+        assert_eq!(
+            network.residual_out_arcs(2).collect::<Vec<_>>(),
+            vec![(3, 3), (4, 0), (0, 3)]
+        );
+    }
+
+    #[test]
+    fn flow_carrying_subgraph() {
+        let (source_id, sink_id, mut network) = network_instance1();
+        let idle = network.add_node(KarzanovNode::new());
+        network.connect(1, idle, KarzanovArc::new(4));
+        maxflow(source_id, sink_id, &mut network).unwrap();
+
+        let (subgraph, old_new_map) = network.flow_carrying_subgraph();
+        assert_eq!(old_new_map.len(), 6);
+        assert!(!old_new_map.contains_key(&idle));
+        assert_eq!(subgraph.arc_data.len(), 7);
+        assert!(subgraph.arc_data.iter().flatten().all(|arc| arc.flow > 0));
+        assert_eq!(flow_value(old_new_map[&source_id], &subgraph), 5);
+    }
+
+    #[test]
+    fn capacity_balance_warnings() {
+        let (source, sink, network) = network_instance3();
+        assert!(network.capacity_balance_warnings(source, sink).is_empty());
+
+        // 0 -(100)-> 1 -(1)-> 2
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 3].into_iter());
+        network.connect(0, 1, KarzanovArc::new(100));
+        network.connect(1, 2, KarzanovArc::new(1));
+        assert_eq!(network.capacity_balance_warnings(0, 2), vec![(1, 100, 1)]);
+    }
+
+    #[test]
+    fn solve_repeatedly() {
+        let (source, sink, network) = network_instance1();
+        let (value, _) = network.solve_repeatedly(source, sink, 2).unwrap();
+        assert_eq!(value, 5);
+        assert!(network.arc_data.iter().flatten().all(|arc| arc.flow == 0));
+    }
+
+    #[test]
+    fn all_nodes_balanced() {
+        for (source, sink, mut network) in [
+            network_instance1(),
+            network_instance2(),
+            network_instance3(),
+            network_instance4(),
+        ] {
+            maxflow(source, sink, &mut network).unwrap();
+            assert!(network.all_nodes_balanced(source, sink));
+        }
+
+        // a preflow stuck at node 1
+        let (source, sink, mut network) = network_instance3();
+        network.mut_data_of_arc(0).unwrap().flow = 1;
+        assert!(!network.all_nodes_balanced(source, sink));
+    }
+
+    #[test]
+    fn node_stack() {
+        let (source, sink, mut network) = network_instance1();
+        let layers = grouping_nodes_by_layer(source, sink, &mut network).unwrap();
+        maximize_outgoing(
+            &layers,
+            0,
+            &mut network,
+            None,
+            false,
+            &mut MaxFlowStats::default(),
+        )
+        .unwrap();
+        // the source saturates both of its arcs
+        assert_eq!(network.node_stack(1), Some(&[(0, 2)][..]));
+        assert_eq!(network.node_stack(2), Some(&[(1, 3)][..]));
+        // node 1 passes all of its 2 on
+        assert!(network.node_stack(3).unwrap().contains(&(2, 2)));
+        assert_eq!(network.node_stack(source), Some(&[][..]));
+
+        network.remove_node(4);
+        assert_eq!(network.node_stack(4), None);
+    }
+
+    #[test]
+    fn auto_single_sink_and_source() {
+        // two sources 0 and 1 feeding two sinks 2 and 3
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 2, KarzanovArc::new(2)),
+                (0, 3, KarzanovArc::new(1)),
+                (1, 3, KarzanovArc::new(4)),
+            ]
+            .into_iter(),
+        );
+        let sink = network.auto_single_sink();
+        let source = network.auto_single_source();
+        assert_eq!((source, sink), (5, 4));
+        assert_eq!(network.from_node(sink).count(), 0);
+        assert_eq!(network.into_node(source).count(), 0);
+
+        assert!(network.explain_layering(source, sink).is_ok());
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(flow_value(source, &network), 7);
+    }
+
+    #[test]
+    fn flow_along() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        // the whole flow of 0 -> 1 goes on to 3 and 5
+        assert_eq!(network.flow_along(&[0, 1, 3, 5]), 2);
+        assert_eq!(network.flow_along(&[0, 2, 4, 5]), 2);
+        // 0 and 3 are not adjacent
+        assert_eq!(network.flow_along(&[0, 3, 5]), 0);
+        assert_eq!(network.flow_along(&[0]), 0);
+        assert_eq!(network.flow_along(&[0, 9]), 0);
+    }
+
+    #[test]
+    fn min_capacity_for_flow() {
+        let (source, sink, mut network) = network_instance1();
+        // both arcs out of the source gain 1 for a flow of 7
+        assert_eq!(
+            super::min_capacity_for_flow(source, sink, &mut network, 6),
+            Some(1)
+        );
+        assert_eq!(
+            super::min_capacity_for_flow(source, sink, &mut network, 5),
+            Some(0)
+        );
+        assert_eq!(flow_value(source, &network), 5);
+
+        // no path leads to the sink
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 2].into_iter());
+        assert_eq!(super::min_capacity_for_flow(0, 1, &mut network, 1), None);
+        // a zero-capacity arc does, once it gains 3
+        network.connect(0, 1, KarzanovArc::new(0));
+        assert_eq!(super::min_capacity_for_flow(0, 1, &mut network, 3), Some(3));
+    }
+
+    #[test]
+    fn disconnect_and_rebalance() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        // 0 -> 1 carries 2
+        assert_eq!(
+            network.disconnect_and_rebalance(0, source, sink).unwrap(),
+            3
+        );
+        assert!(network.all_nodes_balanced(source, sink));
+        assert!(network
+            .arc_data
+            .iter()
+            .flatten()
+            .all(|arc| arc.flow <= arc.capacity));
+
+        // cut the sink off
+        let (source, sink, mut network) = network_instance3();
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(
+            network.disconnect_and_rebalance(1, source, sink).unwrap(),
+            0
+        );
+        assert!(network.arc_data.iter().flatten().all(|arc| arc.flow == 0));
+
+        // 1 -> 2 -> 1 carries a circulation besides the flow 0 -> 1 -> 3, which stays
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![(0, 1), (1, 3), (1, 2), (2, 1)]
+                .into_iter()
+                .map(|(from, into)| (from, into, KarzanovArc::new(1))),
+        );
+        for arc in network.arc_data.iter_mut().flatten() {
+            arc.flow = 1;
+        }
+        assert_eq!(network.disconnect_and_rebalance(2, 0, 3), Ok(1));
+        assert_eq!(network.data_of_arc(3).unwrap().flow, 0);
+        assert_eq!(super::flow_on(&network, 1, 3), 1);
+        assert_eq!(
+            network.disconnect_and_rebalance(0, 0, 9),
+            Err(MaxFlowError::NodeMissing(9))
+        );
+    }
+
+    #[test]
+    fn layer_widths() {
+        let (source, sink, network) = network_instance2();
+        // 0 | 1 3 | 2 4 6 | 5 7 | 8
+        assert_eq!(network.layer_widths(source, sink), vec![1, 2, 3, 2, 1]);
+        assert_eq!(network.max_layer_width(source, sink), 3);
+        // 3 is not reachable from 1
+        assert_eq!(network.layer_widths(1, 3), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn solve_and_assert_optimal() {
+        let (source, sink, mut network) = network_instance1();
+        assert_eq!(
+            super::solve_and_assert_optimal(source, sink, &mut network),
+            5
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "not maximum")]
+    fn assert_optimal_on_a_poor_flow() {
+        // a valid flow of 2 along 0 -> 1 -> 3 -> 5 only
+        let (source, sink, mut network) = network_instance1();
+        for arc_id in [0, 2, 5] {
+            network.mut_data_of_arc(arc_id).unwrap().flow = 2;
+        }
+        assert_optimal(source, sink, &network);
+    }
+
+    #[test]
+    fn residual_between() {
+        let (_, _, mut network) = network_instance1();
+        // 2 -> 3 carries 1 of 4
+        network.mut_data_of_arc(3).unwrap().flow = 1;
+        assert_eq!(network.residual_between(2, 3), (3, 1));
+        // the arcs of 3 -> 2 only
+        assert_eq!(network.residual_between(3, 2), (0, 0));
+
+        // two parallel arcs whose sums do not fit
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 2].into_iter());
+        for flow in [u32::MAX, 1] {
+            let arc_id = network.connect(0, 1, KarzanovArc::new(u32::MAX));
+            network.mut_data_of_arc(arc_id).unwrap().flow = flow;
+        }
+        assert_eq!(network.residual_between(0, 1), (u32::MAX - 1, u32::MAX));
+        network.mut_data_of_arc(0).unwrap().flow = 0;
+        assert_eq!(network.residual_between(0, 1), (u32::MAX, 1));
+
+        // an arc over its capacity adds no room
+        set_capacity(&mut network, 1, 0);
+        assert_eq!(network.residual_between(0, 1), (u32::MAX, 1));
+    }
+
+    #[test]
+    fn maxflow_seeded() {
+        // node 1 may send its unit either through 2 or through 3
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 5].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(1)),
+                (1, 2, KarzanovArc::new(1)),
+                (1, 3, KarzanovArc::new(1)),
+                (2, 4, KarzanovArc::new(1)),
+                (3, 4, KarzanovArc::new(1)),
+            ]
+            .into_iter(),
+        );
+        let mut through_2 = Vec::new();
+        for seed in 0..8 {
+            assert_eq!(super::maxflow_seeded(0, 4, &mut network, seed).unwrap(), 1);
+            let flows = network.flow_map();
+            through_2.push(flows[&(1, 2)] == 1);
+            super::maxflow_seeded(0, 4, &mut network, seed).unwrap();
+            assert_eq!(network.flow_map(), flows);
+        }
+        assert!(through_2.contains(&true) && through_2.contains(&false));
+    }
+
+    #[test]
+    fn inferred_node_throughput_caps() {
+        let (_, _, network) = network_instance1();
+        let caps = network.inferred_node_throughput_caps();
+        // node 3 takes in 2 + 4 but sends out 3
+        assert_eq!(caps[3], 3);
+        assert_eq!(caps, vec![0, 2, 3, 3, 2, 0]);
+
+        // the source takes no special place once an arc leads back into it
+        let (_, _, mut network) = network_instance1();
+        network.connect(1, 0, KarzanovArc::new(1));
+        assert_eq!(network.inferred_node_throughput_caps()[0], 1);
+    }
+
+    #[test]
+    fn lock_saturated_arcs() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        network.lock_saturated_arcs();
+        let committed: Vec<(ArcId, u32)> = network
+            .arc_data
+            .iter()
+            .enumerate()
+            .filter_map(|(arc_id, arc)| {
+                arc.as_ref()
+                    .filter(|arc| arc.locked)
+                    .map(|arc| (arc_id, arc.flow))
+            })
+            .collect();
+        // 2 -> 3 is the only arc left with room
+        assert_eq!(committed.len(), 6);
+        assert!(!network.data_of_arc(3).unwrap().locked);
+
+        network.mut_data_of_arc(3).unwrap().capacity = 10;
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(flow_value(source, &network), 5);
+        for (arc_id, flow) in committed {
+            assert_eq!(network.data_of_arc(arc_id).unwrap().flow, flow);
+        }
+        assert!(network.all_nodes_balanced(source, sink));
+    }
+
+    #[test]
+    fn lock_saturated_arcs_off_a_locked_path() {
+        // 2 -> 3 is saturated and locked, while 1 -> 2 before it keeps room and is solved anew
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![(0, 1, 6), (1, 2, 5), (2, 3, 1), (1, 3, 4)]
+                .into_iter()
+                .map(|(from, into, cap)| (from, into, KarzanovArc::new(cap))),
+        );
+        assert_eq!(maxflow(0, 3, &mut network), Ok(5));
+        network.lock_saturated_arcs();
+        assert!(network.data_of_arc(2).unwrap().locked);
+        assert!(!network.data_of_arc(1).unwrap().locked);
+
+        assert_eq!(maxflow(0, 3, &mut network), Ok(5));
+        assert_eq!(network.data_of_arc(1).unwrap().flow, 1);
+        assert_eq!(super::validate_flow(0, 3, &network), Ok(()));
+
+        // the committed flow of 2 -> 3 can no longer reach 2
+        network.mut_data_of_arc(1).unwrap().capacity = 0;
+        assert_eq!(
+            maxflow(0, 3, &mut network),
+            Err(MaxFlowError::InfeasibleFlow(FlowError::Unbalanced(2)))
+        );
+    }
+
+    #[test]
+    fn compress_chains() {
+        let (source, sink, mut network) = network_instance3();
+        let replaced = network.compress_chains();
+        // the new arc takes a freed id
+        assert_eq!(replaced, HashMap::from([(1, vec![1])]));
+        assert!(!network.is_node_in(1));
+        assert_eq!(network.arc_endpoints(1), Some((0, 2)));
+        assert_eq!(network.data_of_arc(1).unwrap().capacity, 1);
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(flow_value(source, &network), 1);
+
+        // 1 and 4 pass on what they get
+        let (source, sink, mut network) = network_instance1();
+        let replaced = network.compress_chains();
+        assert_eq!(replaced, HashMap::from([(2, vec![1]), (6, vec![4])]));
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(flow_value(source, &network), 5);
+    }
+
+    #[test]
+    fn maxflow_returns_the_value() {
+        for ((source, sink, mut network), expected) in [
+            (network_instance1(), 5),
+            (network_instance2(), 6),
+            (network_instance3(), 1),
+            (network_instance4(), 1),
+        ] {
+            assert_eq!(maxflow(source, sink, &mut network), Ok(expected));
+            assert_eq!(incoming_flux_of_flow(sink, &network), expected);
+        }
+    }
+
+    #[test]
+    fn maxflow_errors() {
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(1)),
+                (0, 2, KarzanovArc::new(1)),
+                (1, 3, KarzanovArc::new(1)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            maxflow(1, 2, &mut network),
+            Err(MaxFlowError::SinkUnreachable)
+        );
+        assert_eq!(
+            maxflow(0, 7, &mut network),
+            Err(MaxFlowError::NodeMissing(7))
+        );
+        network.remove_node(3);
+        assert_eq!(
+            maxflow(3, 2, &mut network),
+            Err(MaxFlowError::NodeMissing(3))
+        );
+    }
+
+    #[test]
+    fn min_cut() {
+        for (source, sink, mut network) in [network_instance1(), network_instance2()] {
+            let value = maxflow(source, sink, &mut network).unwrap();
+            let (source_side, sink_side, cut_arcs) = super::min_cut(source, sink, &network);
+            assert!(source_side.contains(&source) && sink_side.contains(&sink));
+            let mut cut_value = 0;
+            for arc_id in cut_arcs {
+                let (from, into) = network.arc_endpoints(arc_id).unwrap();
+                assert!(source_side.contains(&from) && sink_side.contains(&into));
+                cut_value += network.data_of_arc(arc_id).unwrap().capacity;
+            }
+            assert_eq!(cut_value, value);
+        }
+    }
+
+    #[test]
+    fn decompose_flow() {
+        let (source, sink, mut network) = network_instance2();
+        let value = maxflow(source, sink, &mut network).unwrap();
+        let paths = super::decompose_flow(source, sink, &network);
+        assert_eq!(paths.iter().map(|(_, amount)| amount).sum::<u32>(), value);
+
+        let mut flows = vec![0; network.arc_data.len()];
+        for (path, amount) in &paths {
+            assert_eq!(network.arc_endpoints(path[0]).unwrap().0, source);
+            assert_eq!(
+                network.arc_endpoints(*path.last().unwrap()).unwrap().1,
+                sink
+            );
+            for arc_id in path {
+                flows[*arc_id] += amount;
+            }
+        }
+        for (arc_id, arc) in network.arc_data.iter().enumerate() {
+            assert_eq!(flows[arc_id], arc.as_ref().unwrap().flow);
+        }
+    }
+
+    #[test]
+    fn u64_capacities() {
+        let big = 3_000_000_000u64;
+        let mut network: GraphNetwork<KarzanovNode<u64>, KarzanovArc<u64>> = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::default(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::from_capacity(big)),
+                (0, 2, KarzanovArc::from_capacity(big)),
+                (1, 3, KarzanovArc::from_capacity(big)),
+                (2, 3, KarzanovArc::from_capacity(big + 1)),
+            ]
+            .into_iter(),
+        );
+        // the flow value alone does not fit in u32
+        assert_eq!(maxflow(0, 3, &mut network), Ok(2 * big));
+        assert!(network.all_nodes_balanced(0, 3));
+    }
+
+    #[test]
+    fn from_matrix() {
+        let mut network = GraphNetwork::from_matrix(&[vec![0, 1], vec![0, 0]]);
+        assert_eq!(network.arc_data.len(), 1);
+        assert_eq!(maxflow(0, 1, &mut network), Ok(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "row 1 of the capacity matrix has 1 entries instead of 2")]
+    fn from_matrix_not_square() {
+        GraphNetwork::from_matrix(&[vec![0, 1], vec![0]]);
+    }
+
+    #[test]
+    fn stats() {
+        let (_, _, mut network) = network_instance1();
+        assert_eq!(
+            network.stats(),
+            GraphStats {
+                nodes: 6,
+                arcs: 7,
+                total_capacity: 18,
+                max_degree: 3,
+            }
+        );
+        network.remove_node(3);
+        network.disconnect(0);
+        // 0 -> 2, 2 -> 4 and 4 -> 5 are left
+        assert_eq!(
+            network.stats(),
+            GraphStats {
+                nodes: 5,
+                arcs: 3,
+                total_capacity: 7,
+                max_degree: 2,
+            }
+        );
+    }
+
+    #[test]
+    fn clone_keeps_the_original() {
+        let (source, sink, network) = network_instance1();
+        let mut snapshot = network.clone();
+        assert_eq!(maxflow(source, sink, &mut snapshot), Ok(5));
+        assert!(snapshot.arc_data.iter().flatten().any(|arc| arc.flow > 0));
+        assert!(network.arc_data.iter().flatten().all(|arc| arc.flow == 0));
+    }
+
+    #[test]
+    fn validate_flow() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(super::validate_flow(source, sink, &network), Ok(()));
+
+        // one more on 2 -> 4 leaves 2 short and 4 in excess
+        network.mut_data_of_arc(4).unwrap().flow += 1;
+        assert_eq!(
+            super::validate_flow(source, sink, &network),
+            Err(vec![2, 4])
+        );
+    }
+
+    #[test]
+    fn validate_capacities() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(super::validate_capacities(&network), Ok(()));
+
+        // 1 -> 3 may carry 2
+        network.mut_data_of_arc(2).unwrap().flow = 3;
+        network.disconnect(0);
+        assert_eq!(super::validate_capacities(&network), Err(vec![2]));
+    }
+
+    #[test]
+    fn residual() {
+        let (source, sink, mut network) = network_instance1();
+        assert_eq!(network.residual(3), Some(4));
+        maxflow(source, sink, &mut network).unwrap();
+        for arc_id in 0..network.arc_data.len() {
+            let arc = network.data_of_arc(arc_id).unwrap();
+            assert_eq!(network.residual(arc_id), Some(arc.capacity() - arc.flow()));
+        }
+        // the arcs of the source are saturated
+        assert_eq!(network.residual(0), Some(0));
+        assert_eq!(network.residual(1), Some(0));
+        network.disconnect(0);
+        assert_eq!(network.residual(0), None);
+        assert_eq!(network.residual(99), None);
+    }
+
+    #[test]
+    fn residual_graph() {
+        let (source, sink, mut network) = network_instance1();
+        let residual = super::residual_graph(&network);
+        assert_eq!(residual.arc_count(), 7);
+        assert!(residual.st_participation_mask(source, sink)[sink]);
+
+        for (source, sink, mut network) in [network_instance1(), network_instance2()] {
+            maxflow(source, sink, &mut network).unwrap();
+            let residual = super::residual_graph(&network);
+            assert!(!residual.st_participation_mask(source, sink)[sink]);
+        }
+
+        // 0 -> 1 is saturated, so only its backward arc is left
+        maxflow(source, sink, &mut network).unwrap();
+        network.remove_node(4);
+        let residual = super::residual_graph(&network);
+        assert!(!residual.is_node_in(4));
+        assert_eq!(residual.from_node(0).count(), 0);
+        let backward: Vec<ArcId> = residual.between_nodes(1, 0).collect();
+        assert_eq!(backward.len(), 1);
+        assert_eq!(residual.data_of_arc(backward[0]).unwrap().capacity(), 2);
+
+        // a locked arc keeps its flow, so it leaves nothing behind
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        let arc_count = super::residual_graph(&network).arc_count();
+        network.lock_saturated_arcs();
+        assert!(super::residual_graph(&network).arc_count() < arc_count);
+    }
+
+    #[test]
+    fn layers_by_distance() {
+        // 0 -> 2 skips the layer of 1, so 2 sits next to 1 and 1 -> 2 is no level arc
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(1)),
+                (0, 2, KarzanovArc::new(1)),
+                (1, 2, KarzanovArc::new(1)),
+                (1, 3, KarzanovArc::new(1)),
+                (2, 3, KarzanovArc::new(1)),
+            ]
+            .into_iter(),
+        );
+        let layers = grouping_nodes_by_layer(0, 3, &mut network).unwrap();
+        assert_eq!(layers, vec![vec![0], vec![1, 2], vec![3]]);
+        assert!(!is_level_arc(1, 2, &network));
+
+        let (source, sink, mut network) = network_instance2();
+        let layers = grouping_nodes_by_layer(source, sink, &mut network).unwrap();
+        assert_eq!(
+            layers,
+            vec![vec![0], vec![1, 3], vec![2, 4, 6], vec![5, 7], vec![8]]
+        );
+    }
+
+    #[test]
+    fn sink_anywhere() {
+        // the sink shares its layer with a dead end
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(1)),
+                (0, 2, KarzanovArc::new(1)),
+                (1, 3, KarzanovArc::new(1)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(maxflow(0, 2, &mut network), Ok(1));
+
+        // the sink is reached both at once and over a longer path
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 3, KarzanovArc::new(2)),
+                (0, 1, KarzanovArc::new(3)),
+                (1, 2, KarzanovArc::new(3)),
+                (2, 3, KarzanovArc::new(3)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(maxflow(0, 3, &mut network), Ok(5));
+        assert!(network.all_nodes_balanced(0, 3));
+    }
+
+    #[test]
+    fn maxflow_on_cycle() {
+        // 0 -> 1 -> 2 -> 0 with the sink hanging off the cycle
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(4)),
+                (1, 2, KarzanovArc::new(4)),
+                (2, 0, KarzanovArc::new(4)),
+                (2, 3, KarzanovArc::new(3)),
+                (1, 3, KarzanovArc::new(1)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(network.find_cycle_between(0, 3), Some(0));
+        let mut by_dinic = network.clone();
+        assert_eq!(maxflow(0, 3, &mut network), Ok(4));
+        assert_eq!(crate::dinic::maxflow(0, 3, &mut by_dinic), 4);
+        assert!(network.all_nodes_balanced(0, 3));
+        assert_eq!(super::validate_capacities(&network), Ok(()));
+    }
+
+    #[test]
+    fn maxflow_acyclic() {
+        // 1 -> 2 -> 3 -> 1 on the way from 0 to 4
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 5].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(2)),
+                (1, 2, KarzanovArc::new(2)),
+                (2, 3, KarzanovArc::new(2)),
+                (3, 1, KarzanovArc::new(2)),
+                (3, 4, KarzanovArc::new(2)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            super::maxflow_acyclic(0, 4, &mut network),
+            Err(MaxFlowError::ContainsCycle(1))
+        );
+        assert!(network.arc_data.iter().flatten().all(|arc| arc.flow == 0));
+        assert_eq!(
+            super::maxflow_acyclic(0, 9, &mut network),
+            Err(MaxFlowError::NodeMissing(9))
+        );
+        assert_eq!(maxflow(0, 4, &mut network), Ok(2));
+
+        let (source, sink, mut network) = network_instance1();
+        assert_eq!(super::maxflow_acyclic(source, sink, &mut network), Ok(5));
+    }
+
+    #[test]
+    fn maxflow_general_agrees_with_dinic() {
+        use crate::gen::random_network;
+
+        for seed in 0..500 {
+            let nodes = 2 + seed as usize % 7;
+            let arcs = seed as usize / 7 % (3 * nodes);
+            let (source, sink, network) = random_network(nodes, arcs, 10, seed);
+            let mut by_dinic = network.clone();
+            let mut by_karzanov = network;
+            assert_eq!(
+                maxflow_general(source, sink, &mut by_karzanov),
+                Ok(crate::dinic::maxflow(source, sink, &mut by_dinic)),
+                "seed {}",
+                seed
+            );
+            assert!(
+                by_karzanov.all_nodes_balanced(source, sink),
+                "seed {}",
+                seed
+            );
+            assert_eq!(super::validate_capacities(&by_karzanov), Ok(()));
+        }
+    }
+
+    #[test]
+    fn split_antiparallel() {
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(3)),
+                (1, 2, KarzanovArc::new(2)),
+                (2, 1, KarzanovArc::new(1)),
+                (2, 3, KarzanovArc::new(3)),
+                (1, 3, KarzanovArc::new(1)),
+            ]
+            .into_iter(),
+        );
+        let original = network.clone();
+        let halves = network.split_antiparallel();
+        assert_eq!(halves.len(), 1);
+        let [first_id, second_id] = halves[&2];
+        let middle_id = network.arc_endpoints(first_id).unwrap().1;
+        assert_eq!(network.arc_endpoints(first_id), Some((2, middle_id)));
+        assert_eq!(network.arc_endpoints(second_id), Some((middle_id, 1)));
+        assert_eq!(network.between_nodes(2, 1).count(), 0);
+
+        assert_eq!(maxflow(0, 3, &mut network), Ok(3));
+        let mut recovered = original;
+        for arc_id in 0..recovered.arc_data.len() {
+            let [first_id, second_id] = halves.get(&arc_id).copied().unwrap_or([arc_id; 2]);
+            let flow = network.data_of_arc(first_id).unwrap().flow;
+            assert_eq!(network.data_of_arc(second_id).unwrap().flow, flow);
+            recovered.mut_data_of_arc(arc_id).unwrap().flow = flow;
+        }
+        assert_eq!(flow_value(0, &recovered), 3);
+        assert!(recovered.all_nodes_balanced(0, 3));
+    }
+
+    #[test]
+    fn self_loops_carry_nothing() {
+        let (source, sink, mut network) = network_instance1();
+        let loops: Vec<ArcId> = [source, 1, sink]
+            .into_iter()
+            .map(|node_id| network.connect(node_id, node_id, KarzanovArc::new(7)))
+            .collect();
+        assert_eq!(maxflow(source, sink, &mut network), Ok(5));
+        assert!(network.all_nodes_balanced(source, sink));
+        for arc_id in &loops {
+            assert_eq!(network.data_of_arc(*arc_id).unwrap().flow, 0);
+            assert_eq!(network.data_of_arc(*arc_id).unwrap().capacity, 7);
+        }
+        // the flux functions leave the loops out even when they carry flow
+        network.mut_data_of_arc(loops[1]).unwrap().flow = 7;
+        assert!(network.all_nodes_balanced(source, sink));
+        assert_eq!(flow_value(source, &network), 5);
+    }
+
+    #[test]
+    fn zero_capacity_arcs_define_no_layer() {
+        // 0 -(0)-> 1 leaves 1 to the longer way round, 0 -> 2 -> 1
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(0)),
+                (0, 2, KarzanovArc::new(2)),
+                (2, 1, KarzanovArc::new(1)),
+                (1, 3, KarzanovArc::new(2)),
+                (2, 3, KarzanovArc::new(0)),
+            ]
+            .into_iter(),
+        );
+        let layers = grouping_nodes_by_layer(0, 3, &mut network).unwrap();
+        assert_eq!(layers, vec![vec![0], vec![2], vec![1], vec![3]]);
+
+        assert_eq!(maxflow(0, 3, &mut network), Ok(1));
+        for arc_id in [0, 4] {
+            assert_eq!(network.data_of_arc(arc_id).unwrap().flow, 0);
+        }
+        assert!(network.node_data.iter().flatten().all(|node| node
+            .stack
+            .iter()
+            .all(|(arc_id, _)| ![0, 4].contains(arc_id))));
+
+        network.disconnect(2);
+        assert_eq!(
+            maxflow(0, 3, &mut network),
+            Err(MaxFlowError::SinkUnreachable)
+        );
+    }
+
+    #[test]
+    fn arc_getters() {
+        let (source, sink, mut network) = network_instance1();
+        assert!(network.data_of_arc(0).unwrap().is_open());
+        maxflow(source, sink, &mut network).unwrap();
+        let arc = network.data_of_arc(0).unwrap();
+        assert_eq!((arc.capacity(), arc.flow()), (2, 2));
+
+        network.lock_saturated_arcs();
+        maxflow(source, sink, &mut network).unwrap();
+        assert!(!network.data_of_arc(0).unwrap().is_open());
+        assert_eq!(network.data_of_arc(0).unwrap().flow(), 2);
+    }
+
+    #[test]
+    fn flow_on() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        // both arcs of the source are saturated
+        assert_eq!(super::flow_on(&network, 0, 1), 2);
+        assert_eq!(super::flow_on(&network, 0, 2), 3);
+        network.connect(0, 1, KarzanovArc::new(4));
+        maxflow(source, sink, &mut network).unwrap();
+        assert_eq!(super::flow_on(&network, 0, 1), 2);
+        assert_eq!(super::flow_on(&network, 1, 0), 0);
+        assert_eq!(super::flow_on(&network, 0, 99), 0);
+    }
+
+    #[test]
+    fn maxflow_incremental() {
+        let (source, sink, mut network) = network_instance1();
+        maxflow(source, sink, &mut network).unwrap();
+        // widen 0 -> 2 and 3 -> 5, the bottlenecks on both sides
+        assert_eq!(set_capacity(&mut network, 1, 6), Some(3));
+        assert_eq!(set_capacity(&mut network, 5, 5), Some(3));
+        let mut fresh = network.clone();
+        assert_eq!(
+            super::maxflow_incremental(source, sink, &mut network),
+            Ok(7)
+        );
+        assert_eq!(maxflow(source, sink, &mut fresh), Ok(7));
+        assert!(network.all_nodes_balanced(source, sink));
+
+        // 3 -> 5 carries 5 for a value of 7, so the solve starts over
+        assert_eq!(set_capacity(&mut network, 5, 2), Some(5));
+        assert_eq!(network.residual(5), Some(0));
+        let mut fresh = network.clone();
+        assert_eq!(
+            super::maxflow_incremental(source, sink, &mut network),
+            Ok(4)
+        );
+        assert_eq!(maxflow(source, sink, &mut fresh), Ok(4));
+        assert_eq!(super::validate_capacities(&network), Ok(()));
+
+        assert_eq!(set_capacity(&mut network, 99, 1), None);
+        assert_eq!(
+            super::maxflow_incremental(source, 99, &mut network),
+            Err(MaxFlowError::NodeMissing(99))
+        );
+    }
+
+    #[test]
+    fn maxflow_warmstart() {
+        let (source, sink, mut network) = network_instance2();
+        maxflow(source, sink, &mut network).unwrap();
+        // 5 -> 8 is the bottleneck
+        set_capacity(&mut network, 9, 8);
+        let mut cold = network.clone();
+        let value = maxflow(source, sink, &mut cold).unwrap();
+        assert_eq!(
+            super::maxflow_warmstart(source, sink, &mut network),
+            Ok(value)
+        );
+        assert_eq!(flow_value(source, &network), value);
+        assert!(network.all_nodes_balanced(source, sink));
+
+        set_capacity(&mut network, 9, 0);
+        let before = network.flow_map();
+        assert_eq!(
+            super::maxflow_warmstart(source, sink, &mut network),
+            Err(MaxFlowError::InfeasibleFlow(FlowError::CapacityExceeded(9)))
+        );
+        assert_eq!(network.flow_map(), before);
+        set_capacity(&mut network, 9, 8);
+        let arc_id = network.connect(1, 4, KarzanovArc::new(5));
+        network.mut_data_of_arc(arc_id).unwrap().flow = 1;
+        assert_eq!(
+            super::maxflow_warmstart(source, sink, &mut network),
+            Err(MaxFlowError::InfeasibleFlow(FlowError::Unbalanced(1)))
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_layers() {
+        use crate::gen::random_layered;
+
+        for seed in 0..10 {
+            let (source, sink, mut sequential) = random_layered(3, 64, 10, seed);
+            let layers = grouping_nodes_by_layer(source, sink, &mut sequential).unwrap();
+            let mut parallel = sequential.clone();
+            maximize_outgoing(
+                &layers,
+                0,
+                &mut sequential,
+                None,
+                false,
+                &mut MaxFlowStats::default(),
+            )
+            .unwrap();
+            maximize_outgoing(
+                &layers,
+                0,
+                &mut parallel,
+                None,
+                true,
+                &mut MaxFlowStats::default(),
+            )
+            .unwrap();
+            assert_eq!(parallel.flow_map(), sequential.flow_map(), "seed {}", seed);
+            for node_id in 0..parallel.node_data.len() {
+                let (node, other) = (&parallel[node_id], &sequential[node_id]);
+                assert_eq!(node.stack, other.stack, "seed {}", seed);
+                assert_eq!(node.blocked, other.blocked, "seed {}", seed);
+            }
+
+            let mut by_dinic = parallel.clone();
+            let mut by_maxflow = parallel.clone();
+            assert_eq!(
+                super::maxflow_parallel(source, sink, &mut parallel),
+                Ok(crate::dinic::maxflow(source, sink, &mut by_dinic))
+            );
+            maxflow(source, sink, &mut by_maxflow).unwrap();
+            assert_eq!(parallel.flow_map(), by_maxflow.flow_map(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn feasible_flow() {
+        // 1 -> 3 must carry 2, more than the 1 that 0 -> 1 lets in, so it comes 0 -> 2 -> 1
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::new(1)),
+                (0, 2, KarzanovArc::with_lower(1, 5)),
+                (2, 1, KarzanovArc::new(2)),
+                (1, 3, KarzanovArc::with_lower(2, 3)),
+                (2, 3, KarzanovArc::new(2)),
+            ]
+            .into_iter(),
+        );
+        let value = super::feasible_flow(0, 3, &mut network).unwrap();
+        assert_eq!(flow_value(0, &network), value);
+        assert!(value >= 2);
+        assert!(network.all_nodes_balanced(0, 3));
+        for arc in network.arc_data.iter().flatten() {
+            assert!(arc.lower() <= arc.flow() && arc.flow() <= arc.capacity());
+        }
+
+        // the lower bound of 1 -> 3 exceeds what can ever reach 1
+        network.replace_arc_data(3, KarzanovArc::with_lower(4, 5));
+        assert_eq!(super::feasible_flow(0, 3, &mut network), None);
+        // a lower bound above its capacity
+        network.replace_arc_data(3, KarzanovArc::with_lower(4, 3));
+        assert_eq!(super::feasible_flow(0, 3, &mut network), None);
+        // 0 -> 1 and 0 -> 2 must carry 3e9 each, which sum up past u32 for the demands and the value
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::with_lower(3_000_000_000, 3_000_000_000)),
+                (0, 2, KarzanovArc::with_lower(3_000_000_000, 3_000_000_000)),
+                (1, 3, KarzanovArc::new(u32::MAX)),
+                (2, 3, KarzanovArc::new(u32::MAX)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(super::feasible_flow(0, 3, &mut network), None);
+        assert!(network.arc_data.iter().flatten().all(|arc| arc.flow() == 0));
+        // a chain whose two bounds of 3e9 make demands summing up past u32, while the value fits
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 1, KarzanovArc::with_lower(3_000_000_000, 3_000_000_000)),
+                (1, 2, KarzanovArc::new(u32::MAX)),
+                (2, 3, KarzanovArc::with_lower(3_000_000_000, 3_000_000_000)),
+            ]
+            .into_iter(),
+        );
+        assert_eq!(
+            super::feasible_flow(0, 3, &mut network),
+            Some(3_000_000_000)
+        );
+        assert!(network.all_nodes_balanced(0, 3));
+    }
+
+    #[test]
+    fn connect_undirected() {
+        // a square 0 - 1 - 3 - 2 - 0 with the diagonal 1 - 2, from 0 to 3
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.connect_undirected(0, 1, 3);
+        network.connect_undirected(0, 2, 1);
+        let (one_two, two_one) = network.connect_undirected(1, 2, 2);
+        network.connect_undirected(1, 3, 1);
+        network.connect_undirected(2, 3, 3);
+        // the cut {0} lets 4 pass, and 2 of it cross the diagonal from 1 to 2
+        assert_eq!(maxflow(0, 3, &mut network), Ok(4));
+        assert!(network.all_nodes_balanced(0, 3));
+        assert_eq!(network.data_of_arc(one_two).unwrap().flow(), 2);
+        assert_eq!(network.data_of_arc(two_one).unwrap().flow(), 0);
+        for arc_id in (0..10).step_by(2) {
+            let flows = [arc_id, arc_id + 1].map(|id| network.data_of_arc(id).unwrap().flow());
+            assert!(flows.contains(&0));
+        }
+    }
+
+    #[test]
+    fn defaults() {
+        let (node, new) = (KarzanovNode::<u32>::default(), KarzanovNode::new());
+        assert_eq!(
+            (node.stack, node.layer, node.blocked),
+            (new.stack, new.layer, new.blocked)
+        );
+
+        let arc = KarzanovArc::<u64>::default();
+        assert_eq!((arc.capacity(), arc.flow(), arc.lower()), (0, 0, 0));
+        assert!(!arc.is_open());
+
+        // whole networks of defaults
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![Default::default(); 3].into_iter());
+        network.bulk_connect(
+            vec![(0, 1, Default::default()), (1, 2, KarzanovArc::new(1))].into_iter(),
+        );
+        assert_eq!(
+            maxflow(0, 2, &mut network),
+            Err(MaxFlowError::SinkUnreachable)
+        );
+    }
+
+    #[test]
+    fn public_layers() {
+        let (source, sink, network) = network_instance1();
+        assert_eq!(
+            layers(source, sink, &network),
+            Ok(vec![vec![0], vec![1, 2], vec![3, 4], vec![5]])
+        );
+        assert!(network
+            .node_data
+            .iter()
+            .flatten()
+            .all(|node| node.layer.is_none()));
+        assert_eq!(
+            layers(sink, source, &network),
+            Err(MaxFlowError::SinkUnreachable)
+        );
+        assert!(network
+            .node_data
+            .iter()
+            .flatten()
+            .all(|node| node.layer.is_none()));
+    }
+
+    #[test]
+    fn order_inside_layer() {
+        // a = 1, b = 2 and c = 3 share the layer of distance 1, with 1 -> 2, 2 -> 3 and 1 -> 3 inside it
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 5].into_iter());
+        network.bulk_connect(
+            vec![
+                (0, 3, KarzanovArc::new(1)),
+                (0, 2, KarzanovArc::new(1)),
+                (0, 1, KarzanovArc::new(1)),
+                (1, 3, KarzanovArc::new(1)),
+                (2, 3, KarzanovArc::new(1)),
+                (1, 2, KarzanovArc::new(1)),
+                (3, 4, KarzanovArc::new(1)),
+            ]
+            .into_iter(),
+        );
+        let (_, layer_of) = network.breadth_first_layers(0);
+        for layer in [[1, 2, 3], [3, 2, 1], [2, 3, 1], [3, 1, 2]] {
+            assert_eq!(
+                network.order_inside_layer(&layer, &layer_of),
+                Ok(vec![1, 2, 3])
+            );
+        }
+    }
+
+    #[test]
+    fn same_source_and_sink() {
+        let (_, _, mut network) = network_instance1();
+        maxflow(0, 5, &mut network).unwrap();
+        assert_eq!(maxflow(2, 2, &mut network), Ok(0));
+        assert!(network.arc_data.iter().flatten().all(|arc| arc.flow == 0));
+        assert_eq!(maxflow_general(2, 2, &mut network), Ok(0));
+        network.remove_node(2);
+        assert_eq!(
+            maxflow(2, 2, &mut network),
+            Err(MaxFlowError::NodeMissing(2))
+        );
+    }
+
+    #[test]
+    fn degenerate_networks() {
+        let mut network = GraphNetwork::<KarzanovNode, KarzanovArc>::new();
+        assert_eq!(
+            maxflow(0, 1, &mut network),
+            Err(MaxFlowError::NodeMissing(0))
+        );
+
+        network.add_nodes(vec![KarzanovNode::new(); 2].into_iter());
+        assert_eq!(
+            maxflow(0, 1, &mut network),
+            Err(MaxFlowError::SinkUnreachable)
+        );
+        assert_eq!(maxflow_general(0, 1, &mut network), Ok(0));
+
+        // 0 -> 1 and 2 -> 3 apart from each other
+        network.add_nodes(vec![KarzanovNode::new(); 2].into_iter());
+        network.bulk_connect(
+            vec![(0, 1, KarzanovArc::new(4)), (2, 3, KarzanovArc::new(4))].into_iter(),
+        );
+        assert_eq!(
+            maxflow(0, 3, &mut network),
+            Err(MaxFlowError::SinkUnreachable)
+        );
+        assert_eq!(maxflow_general(0, 3, &mut network), Ok(0));
+        assert!(network.arc_data.iter().flatten().all(|arc| arc.flow == 0));
+    }
+
+    #[test]
+    fn capacity_overflow() {
+        // three arcs into 1 of 2^31 each, which u32 cannot sum up
+        let pairs = [(0, 1), (0, 1), (0, 1), (1, 2)];
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 3].into_iter());
+        network.bulk_connect(
+            pairs
+                .into_iter()
+                .map(|(from, into)| (from, into, KarzanovArc::new(1 << 31))),
+        );
+        assert_eq!(
+            maxflow(0, 2, &mut network),
+            Err(MaxFlowError::CapacityOverflow(0))
+        );
+
+        // u64 capacities hold the sum
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::<u64>::default(); 3].into_iter());
+        network.bulk_connect(
+            pairs
+                .into_iter()
+                .map(|(from, into)| (from, into, KarzanovArc::from_capacity(1u64 << 31))),
+        );
+        assert_eq!(maxflow(0, 2, &mut network), Ok(1 << 31));
+
+        // the capacities into 2 overflow, but the flux never gets near it
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 3].into_iter());
+        network.bulk_connect(
+            vec![(0, 1, 10), (1, 2, u32::MAX), (1, 2, u32::MAX)]
+                .into_iter()
+                .map(|(from, into, capacity)| (from, into, KarzanovArc::new(capacity))),
+        );
+        assert_eq!(maxflow(0, 2, &mut network), Ok(10));
+    }
+
+    #[test]
+    fn invariant_violation() {
+        // 0 -(4)-> 1 -(4)-> 2 with a broken state instead of wrapping around
+        let make = || {
+            let mut network = GraphNetwork::new();
+            network.add_nodes(vec![KarzanovNode::new(); 3].into_iter());
+            network.bulk_connect(
+                vec![(0, 1, 4), (1, 2, 4)]
+                    .into_iter()
+                    .map(|(from, into, cap)| (from, into, KarzanovArc::new(cap))),
+            );
+            let layers = grouping_nodes_by_layer(0, 2, &mut network).unwrap();
+            (layers, network)
+        };
+
+        // an arc carrying more than its capacity
+        let (layers, mut network) = make();
+        network.mut_data_of_arc(0).unwrap().flow = 5;
+        assert_eq!(
+            maximize_outgoing(
+                &layers,
+                0,
+                &mut network,
+                None,
+                false,
+                &mut MaxFlowStats::default()
+            ),
+            Err(MaxFlowError::InvariantViolation(
+                FlowError::CapacityExceeded(0)
+            ))
+        );
+
+        // the stack of a blocked node records a push its outgoing arc never got
+        let (layers, mut network) = make();
+        network.mut_data_of_arc(0).unwrap().flow = 3;
+        network[1].blocked = true;
+        network[1].stack.push((1, 3));
+        assert_eq!(
+            balance_incoming(&layers, &mut network, &mut MaxFlowStats::default()),
+            Err(MaxFlowError::InvariantViolation(FlowError::NegativeFlow(1)))
+        );
+
+        // a node sending on more than it got
+        let (layers, mut network) = make();
+        network.mut_data_of_arc(1).unwrap().flow = 2;
+        network[1].blocked = true;
+        assert_eq!(
+            balance_incoming(&layers, &mut network, &mut MaxFlowStats::default()),
+            Err(MaxFlowError::InvariantViolation(FlowError::Unbalanced(1)))
+        );
+    }
+
+    #[test]
+    fn bipartite_matching() {
+        // left 0 and 1 both only like right 0, so one of them stays alone: the maximum is 3 of 4
+        let edges = [(0, 0), (1, 0), (2, 0), (2, 1), (3, 1), (3, 2)];
+        let matching = super::bipartite_matching(4, 3, &edges).unwrap();
+        assert_eq!(matching.len(), 3);
+        let mut lefts: Vec<usize> = matching.iter().map(|(l, _)| *l).collect();
+        let mut rights: Vec<usize> = matching.iter().map(|(_, r)| *r).collect();
+        lefts.sort();
+        lefts.dedup();
+        rights.sort();
+        rights.dedup();
+        assert_eq!((lefts.len(), rights.len()), (3, 3));
+        assert!(matching.iter().all(|edge| edges.contains(edge)));
+
+        assert_eq!(super::bipartite_matching(2, 2, &[]), Ok(vec![]));
+    }
+
+    #[test]
+    fn edge_disjoint_paths() {
+        // two parallel paths 0 -> 1 -> 3 and 0 -> 2 -> 3 of wide arcs, with the crossing 1 -> 2
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 4].into_iter());
+        network.bulk_connect(
+            vec![(0, 1, 5), (0, 2, 7), (1, 3, 9), (2, 3, 4), (1, 2, 3)]
+                .into_iter()
+                .map(|(from, into, cap)| (from, into, KarzanovArc::new(cap))),
+        );
+        assert_eq!(super::edge_disjoint_paths(0, 3, &network), Ok(2));
+        assert!(network.arc_data.iter().flatten().all(|arc| arc.flow == 0));
+        assert_eq!(network.data_of_arc(2).unwrap().capacity, 9);
+
+        let paths = edge_disjoint_path_arcs(0, 3, &network).unwrap();
+        assert_eq!(paths.len(), 2);
+        let mut arcs: Vec<ArcId> = paths.concat();
+        let len = arcs.len();
+        arcs.sort();
+        arcs.dedup();
+        assert_eq!(arcs.len(), len);
+
+        // the sink out of reach, or missing
+        assert_eq!(super::edge_disjoint_paths(3, 0, &network), Ok(0));
+        assert_eq!(
+            super::edge_disjoint_paths(0, 9, &network),
+            Err(MaxFlowError::NodeMissing(9))
+        );
+    }
+
+    #[test]
+    fn vertex_disjoint_paths() {
+        // 0 -> 3 only goes on through 1, which has two arcs into the sink 4
+        let mut network = GraphNetwork::new();
+        network.add_nodes(vec![KarzanovNode::new(); 5].into_iter());
+        network.bulk_connect(
+            vec![(0, 1), (0, 2), (0, 3), (1, 4), (2, 4), (3, 1), (1, 4)]
+                .into_iter()
+                .map(|(from, into)| (from, into, KarzanovArc::new(3))),
+        );
+        // three arcs into the sink, but two of them leave node 1
+        assert_eq!(super::edge_disjoint_paths(0, 4, &network), Ok(3));
+        assert_eq!(super::vertex_disjoint_paths(0, 4, &network), Ok(2));
+        let mut without_sink = network.clone();
+        without_sink.remove_node(4);
+        assert_eq!(
+            super::vertex_disjoint_paths(0, 4, &without_sink),
+            Err(MaxFlowError::NodeMissing(4))
+        );
+
+        let (split, halves) = split_nodes(0, 4, &network);
+        assert_eq!(split.node_data.len(), 8);
+        assert_eq!(halves[&0], (0, 0));
+        assert_eq!(halves[&4], (4, 4));
+        let (in_id, out_id) = halves[&1];
+        assert_eq!(in_id, 1);
+        assert_eq!(split.from_node(in_id).count(), 1);
+        assert_eq!(split.from_node(out_id).count(), 2);
+    }
+
+    #[test]
+    fn maxflow_with_stats() {
+        let (source, sink, mut network) = network_instance2();
+        let (value, stats) = super::maxflow_with_stats(source, sink, &mut network).unwrap();
+        assert_eq!(Ok(value), maxflow(source, sink, &mut network.clone()));
+        assert!(stats.iterations > 0 && stats.balancing_passes > 0);
+        assert!(stats.arcs_saturated > 0);
+        // every flux pushed forward reaches the sink or goes back
+        assert!(stats.flow_pushed >= value as u64);
+        // a blocking flow takes at most a pass per node, and the sink is at most n layers away
+        let nodes = network.node_data.len();
+        assert!(stats.iterations <= nodes * nodes);
+    }
+
+    #[test]
+    fn iteration_ceiling() {
+        use crate::dinic;
+        use crate::gen::random_network;
+
+        // large networks with cycles and antiparallel arcs still settle well below the ceiling
+        for seed in 0..5 {
+            let (source, sink, mut network) = random_network(300, 3000, 50, seed);
+            let ceiling = super::iteration_ceiling(&network);
+            let mut by_dinic = network.clone();
+            let (value, stats) = super::maxflow_with_stats(source, sink, &mut network).unwrap();
+            assert_eq!(
+                value,
+                dinic::maxflow(source, sink, &mut by_dinic),
+                "seed {}",
+                seed
+            );
+            assert!(stats.iterations < ceiling, "seed {}", seed);
+        }
     }
 }
@@ -1,3 +1,18 @@
+#[cfg(test)]
+mod brute;
+pub mod codec;
+pub mod csv_io;
+pub mod dimacs;
+pub mod dinic;
+pub mod edmonds_karp;
+pub mod export;
+pub mod float;
+pub mod fuzz;
+pub mod gen;
 pub mod graph;
+pub mod instances;
 pub mod karzanov;
+pub mod offload;
+#[cfg(feature = "petgraph")]
+pub mod petgraph_io;
 mod utils;
@@ -0,0 +1,122 @@
+use crate::graph::{GraphNetwork, NodeId};
+use crate::karzanov::{maxflow, KarzanovArc, KarzanovNode, MaxFlowError};
+
+/// solve a copy of a shared network and return the flow value, leaving `plan` as it is.
+/// it blocks until the solve is done, so an async server should run it on a blocking thread, e.g.
+/// `tokio::task::spawn_blocking(move || solve_blocking(&plan, source, sink))`
+pub fn solve_blocking(
+    plan: &GraphNetwork<KarzanovNode, KarzanovArc>,
+    source_id: NodeId,
+    sink_id: NodeId,
+) -> Result<u32, MaxFlowError> {
+    let mut network = plan.clone();
+    maxflow(source_id, sink_id, &mut network)
+}
+
+#[cfg(feature = "async")]
+mod future {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Waker};
+
+    use super::solve_blocking;
+    use crate::graph::{GraphNetwork, NodeId};
+    use crate::karzanov::{KarzanovArc, KarzanovNode, MaxFlowError};
+
+    #[derive(Default)]
+    struct Shared {
+        flow_value: Option<Result<u32, MaxFlowError>>,
+        waker: Option<Waker>,
+    }
+
+    /// resolves once the solving thread stores the flow value
+    struct Solving(Arc<Mutex<Shared>>);
+
+    impl Future for Solving {
+        type Output = Result<u32, MaxFlowError>;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+            let mut shared = self.0.lock().unwrap();
+            match shared.flow_value.take() {
+                Some(flow_value) => Poll::Ready(flow_value),
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    /// solve a copy of a shared network on its own thread, so that the executor is never blocked.
+    /// it works on any executor, as it only relies on the waker
+    pub async fn solve_async(
+        plan: Arc<GraphNetwork<KarzanovNode, KarzanovArc>>,
+        source_id: NodeId,
+        sink_id: NodeId,
+    ) -> Result<u32, MaxFlowError> {
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let solving = Solving(shared.clone());
+        std::thread::spawn(move || {
+            let flow_value = solve_blocking(&plan, source_id, sink_id);
+            let mut shared = shared.lock().unwrap();
+            shared.flow_value = Some(flow_value);
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        });
+        solving.await
+    }
+}
+
+#[cfg(feature = "async")]
+pub use future::solve_async;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instances::network_instance1;
+
+    #[test]
+    fn solve_blocking_keeps_the_plan() {
+        let (source, sink, plan) = network_instance1();
+        assert_eq!(solve_blocking(&plan, source, sink), Ok(5));
+        assert!(plan.arc_data.iter().flatten().all(|arc| arc.flow == 0));
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn solve_async_on_shared_plan() {
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake, Waker};
+        use std::thread::Thread;
+
+        struct Unpark(Thread);
+        impl Wake for Unpark {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+        fn block_on<F: Future>(future: F) -> F::Output {
+            let waker = Waker::from(Arc::new(Unpark(std::thread::current())));
+            let mut cx = Context::from_waker(&waker);
+            let mut future = std::pin::pin!(future);
+            loop {
+                match future.as_mut().poll(&mut cx) {
+                    Poll::Ready(output) => return output,
+                    Poll::Pending => std::thread::park(),
+                }
+            }
+        }
+
+        let (source, sink, plan) = network_instance1();
+        let plan = Arc::new(plan);
+        let first = solve_async(plan.clone(), source, sink);
+        let second = solve_async(plan.clone(), source, sink);
+        assert_eq!(
+            block_on(async { (first.await, second.await) }),
+            (Ok(5), Ok(5))
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use petgraph::graph::{Graph, NodeIndex};
+
+use crate::graph::{GraphNetwork, NodeId};
+
+impl<N: Clone, A: Clone> GraphNetwork<N, A> {
+    /// a `petgraph::Graph` of the live nodes and arcs with their data, in the order of their ids,
+    /// and the map from the node ids to the petgraph indices
+    pub fn to_petgraph(&self) -> (Graph<N, A>, HashMap<NodeId, NodeIndex>) {
+        let mut graph = Graph::with_capacity(self.node_count(), self.arc_count());
+        let mut indices = HashMap::new();
+        for (node_id, node) in self.node_data.iter().enumerate() {
+            if let Some(node) = node {
+                indices.insert(node_id, graph.add_node(node.clone()));
+            }
+        }
+        for (arc_id, arc) in self.arc_data.iter().enumerate() {
+            if let (Some(arc), Some((from, into))) = (arc, self.arc_endpoints(arc_id)) {
+                graph.add_edge(indices[&from], indices[&into], arc.clone());
+            }
+        }
+        (graph, indices)
+    }
+
+    /// a network of the nodes and edges of a `petgraph::Graph` with their data, keeping the directions.
+    /// the node of index `i` gets the id `i`, and the edges are connected in the order of their indices
+    pub fn from_petgraph(graph: &Graph<N, A>) -> Self {
+        let mut network = GraphNetwork::new();
+        network.add_nodes(graph.node_weights().cloned());
+        for edge in graph.raw_edges() {
+            network.connect(
+                edge.source().index(),
+                edge.target().index(),
+                edge.weight.clone(),
+            );
+        }
+        network
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use petgraph::graph::Graph;
+
+    use crate::graph::GraphNetwork;
+    use crate::instances::network_instance1;
+
+    #[test]
+    fn to_petgraph() {
+        let (_, _, mut network) = network_instance1();
+        network.remove_node(4);
+        let (graph, indices) = network.to_petgraph();
+        assert_eq!(graph.node_count(), network.node_count());
+        assert_eq!(graph.edge_count(), network.arc_count());
+        assert!(!indices.contains_key(&4));
+        let edge = graph.find_edge(indices[&0], indices[&1]).unwrap();
+        assert_eq!(graph[edge].capacity(), 2);
+    }
+
+    #[test]
+    fn from_petgraph() {
+        let mut graph = Graph::<char, u32>::new();
+        let [a, b, c] = ['a', 'b', 'c'].map(|name| graph.add_node(name));
+        graph.extend_with_edges([(a, b, 3), (b, c, 1), (c, a, 2), (a, c, 5)]);
+        let network = GraphNetwork::from_petgraph(&graph);
+        assert_eq!(network.data_of_node(2), Some(&'c'));
+        assert_eq!(network.arc_endpoints(2), Some((2, 0)));
+        assert_eq!(network.data_of_arc(3), Some(&5));
+
+        let (again, indices) = network.to_petgraph();
+        assert!((0..3).all(|node_id| indices[&node_id].index() == node_id));
+        let edges = |graph: &Graph<char, u32>| -> Vec<(usize, usize, u32)> {
+            graph
+                .raw_edges()
+                .iter()
+                .map(|edge| (edge.source().index(), edge.target().index(), edge.weight))
+                .collect()
+        };
+        assert_eq!(edges(&again), edges(&graph));
+        assert!(again.node_weights().eq(graph.node_weights()));
+    }
+}